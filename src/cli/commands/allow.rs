@@ -0,0 +1,58 @@
+use crate::{
+    config::{layer, trust},
+    Config,
+};
+use clap::Args;
+use std::{env, error::Error, fs, path::PathBuf};
+
+#[derive(Args)]
+pub struct Allow {
+    /// Project config, or the directory holding it (default: the nearest one applying here)
+    path: Option<PathBuf>,
+
+    /// Stop applying the project config
+    #[arg(long)]
+    revoke: bool,
+}
+
+impl Allow {
+    /// Trust a project config as it reads now, so `env` applies it until it changes
+    pub fn execute(&self, config: &Config) -> Result<(), Box<dyn Error>> {
+        let project = match &self.path {
+            Some(path) if path.is_dir() => path.join(layer::PROJECT_CONFIG),
+            Some(path) => path.clone(),
+            None => layer::project_config(&config.config_path).ok_or_else(|| {
+                format!(
+                    "No {} in {} or its parents",
+                    layer::PROJECT_CONFIG,
+                    env::current_dir().unwrap_or_default().display()
+                )
+            })?,
+        };
+        //@ Lookups start from the working directory, which never holds symlinks
+        let project = fs::canonicalize(&project)
+            .map_err(|err| format!("Could not access {}: {}", project.display(), err))?;
+
+        let mut trusted = trust::Trusted::read(&config.config_path);
+        if self.revoke {
+            if trusted.files.shift_remove(&project).is_none() {
+                println!("{} was not allowed", project.display());
+                return Ok(());
+            }
+            trusted.write(&config.config_path)?;
+            println!("Revoked {}", project.display());
+            return Ok(());
+        }
+
+        //@ Allow only what parses, so a broken file is not trusted in a later, fixed form
+        let content = fs::read_to_string(&project)?;
+        Config::parse(&project, &content)?;
+        trusted.files.insert(project.clone(), content);
+        trusted.write(&config.config_path)?;
+        println!(
+            "Allowed {}; `dotsrus env` applies it until it changes",
+            project.display()
+        );
+        Ok(())
+    }
+}
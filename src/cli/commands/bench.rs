@@ -0,0 +1,262 @@
+use crate::{
+    config::{cache, discover, entry, exclude, layer, migrate, state, variable},
+    shell::Shell,
+    Config,
+};
+use clap::{Args, Subcommand};
+use indexmap::IndexMap;
+use std::{
+    collections::VecDeque,
+    env,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, Instant},
+};
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Time full scans at each thread count, and an unchanged rescan
+    Scan(Scan),
+
+    /// Time `env` from the binary cache against parsing the TOML config
+    Cache(CacheBench),
+}
+
+impl Commands {
+    /// Run a benchmark on synthetic data, leaving the user's own config alone
+    pub fn execute(&self) -> Result<(), Box<dyn Error>> {
+        let root = env::temp_dir().join(format!("dotsrus-bench-{}", process::id()));
+        let result = match self {
+            Self::Scan(scan) => scan.run(&root),
+            Self::Cache(cache) => cache.run(&root),
+        };
+        fs::remove_dir_all(&root)?;
+        result
+    }
+}
+
+#[derive(Args)]
+pub struct Scan {
+    /// Number of directories in the synthetic tree
+    #[arg(long, default_value_t = 20_000)]
+    dirs: usize,
+
+    /// Thread counts to compare, comma-separated (0 picks automatically)
+    #[arg(long, value_delimiter = ',', default_values_t = [1, 0])]
+    jobs: Vec<usize>,
+
+    /// Runs per measurement; the fastest is reported
+    #[arg(long, default_value_t = 3)]
+    runs: usize,
+}
+
+impl Scan {
+    fn run(&self, root: &Path) -> Result<(), Box<dyn Error>> {
+        let depth = create_tree(&root.join("tree"), self.dirs)?;
+        let mut entry = bench_entry(&root.join("tree"), depth);
+        let rules = exclude::Rules::new(&entry.path, &[], &exclude::default(), false)?;
+
+        println!(
+            "Scanning {} directories, {} levels deep (best of {}):",
+            self.dirs,
+            depth,
+            self.runs.max(1)
+        );
+
+        let mut baseline: Option<(Duration, IndexMap<PathBuf, exclude::Check>)> = None;
+        for &jobs in &self.jobs {
+            let (elapsed, discovered) = measure(self.runs, || discover::scan(&entry, &rules, jobs));
+            let label = match jobs {
+                0 => "auto".to_string(),
+                jobs => jobs.to_string(),
+            };
+
+            match &baseline {
+                None => {
+                    println!("  full scan, jobs {}: {:.1?}", label, elapsed);
+                    baseline = Some((elapsed, discovered));
+                }
+                Some((first, expected)) => {
+                    //@ Every thread count must produce the same directories in the same order
+                    if !discovered.keys().eq(expected.keys()) {
+                        return Err(
+                            format!("Scan with jobs {} gave a different result", label).into()
+                        );
+                    }
+                    println!(
+                        "  full scan, jobs {}: {:.1?} ({:.2}x)",
+                        label,
+                        elapsed,
+                        first.as_secs_f64() / elapsed.as_secs_f64()
+                    );
+                }
+            }
+        }
+
+        if let Some((_, discovered)) = baseline {
+            entry.discovered_paths = discovered;
+            let (elapsed, _) = measure(self.runs, || discover::rescan(&entry, &rules));
+            println!("  rescan, nothing changed: {:.1?}", elapsed);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+pub struct CacheBench {
+    /// Number of scanned directories in the synthetic config
+    #[arg(long, default_value_t = 2_000)]
+    dirs: usize,
+
+    /// Number of managed variables in the synthetic config
+    #[arg(long, default_value_t = 50)]
+    variables: usize,
+
+    /// Runs per measurement; the fastest is reported
+    #[arg(long, default_value_t = 20)]
+    runs: usize,
+}
+
+impl CacheBench {
+    fn run(&self, root: &Path) -> Result<(), Box<dyn Error>> {
+        let tree = root.join("tree");
+        let depth = create_tree(&tree, self.dirs)?;
+        let mut entry = bench_entry(&tree, depth);
+        let rules = exclude::Rules::new(&tree, &[], &exclude::default(), false)?;
+        entry.discovered_paths = discover::scan(&entry, &rules, 0);
+
+        //@ State and caches live outside the benchmark directory, so remove them however we return
+        let config_path = root.join("config.toml");
+        let _state = RemoveOnDrop(state::dir(&config_path));
+
+        let mut synthetic = Config {
+            version: migrate::CURRENT_VERSION,
+            path_entries: vec![entry],
+            config_path,
+            default_excludes: exclude::default(),
+            variables: Vec::new(),
+            layers: layer::Layers::default(),
+        };
+        for index in 0..self.variables {
+            synthetic.set_variable(entry::Variable {
+                name: format!("DOTSRUS_BENCH_{}", index),
+                value: tree.join(format!("value-{}", index)).display().to_string(),
+                original: None,
+                kind: variable::Kind::Plain,
+                scope: variable::Scope::Export,
+                lookup_order: Vec::new(),
+            });
+        }
+        synthetic.save()?;
+        //@ Rebuild the cache from the loaded config, so any system layer is in both
+        Config::new(Some(&synthetic.config_path))?.write_cache()?;
+
+        let config_path = synthetic.config_path.as_path();
+        let cache_path = cache::path(config_path);
+        let shell = Shell::Sh;
+        println!(
+            "Rendering {} directories and {} variables (best of {}):",
+            self.dirs,
+            self.variables,
+            self.runs.max(1)
+        );
+
+        //@ Loading reads the scan state as well as the config
+        let (parse, _) = measure(self.runs, || Config::new(Some(config_path)));
+        let (decode, _) = measure(self.runs, || cache::Cache::read(&cache_path));
+        println!("  load config:  {:.1?}", parse);
+        println!("  decode cache: {:.1?}", decode);
+
+        //@ The whole of `env`: load, check freshness where cached, then render
+        let (from_toml, script) = measure(self.runs, || -> Result<String, Box<dyn Error>> {
+            Ok(shell.render(&Config::new(Some(config_path))?)?)
+        });
+        let (from_cache, cached_script) = measure(self.runs, || -> Option<String> {
+            let hash = layer::checksum(config_path).ok()?;
+            let cache = cache::Cache::read(&cache_path).filter(|cache| cache.is_fresh(hash))?;
+            shell
+                .render_resolved(&cache.prepend, &cache.append, &cache.variables)
+                .ok()
+        });
+
+        if cached_script != Some(script?) {
+            return Err("Cached environment differs from the one rendered from TOML".into());
+        }
+        println!("  env from TOML:  {:.1?}", from_toml);
+        println!(
+            "  env from cache: {:.1?} ({:.2}x)",
+            from_cache,
+            from_toml.as_secs_f64() / from_cache.as_secs_f64()
+        );
+        Ok(())
+    }
+}
+
+/// A directory outside the benchmark directory, removed when the benchmark ends
+struct RemoveOnDrop(PathBuf);
+
+impl Drop for RemoveOnDrop {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn bench_entry(root: &Path, max_depth: u8) -> entry::Path {
+    entry::Path {
+        path: root.to_path_buf(),
+        prepend: false,
+        priority: 0,
+        exclude_patterns: Vec::new(),
+        max_depth,
+        min_depth: 0,
+        follow_symlinks: false,
+        order: discover::Order::default(),
+        bins_only: false,
+        discovered_paths: IndexMap::new(),
+        rules_hash: 0,
+    }
+}
+
+/// Run `task` `runs` times, returning the fastest time and the last result
+fn measure<T>(runs: usize, mut task: impl FnMut() -> T) -> (Duration, T) {
+    let mut best = None;
+    let mut result = None;
+    for _ in 0..runs.max(1) {
+        let start = Instant::now();
+        let output = task();
+        let elapsed = start.elapsed();
+        if best.is_none_or(|best| elapsed < best) {
+            best = Some(elapsed);
+        }
+        result = Some(output);
+    }
+    (best.unwrap_or_default(), result.expect("at least one run"))
+}
+
+/// Create `count` directories below `root`, breadth first, returning the tree depth.
+fn create_tree(root: &Path, count: usize) -> Result<u8, Box<dyn Error>> {
+    //@ Pick a fan-out that keeps the tree within four levels
+    let fanout = ((count as f64).powf(0.25).ceil() as usize).max(2);
+    let mut pending = VecDeque::from([(root.to_path_buf(), 0u8)]);
+    let mut created = 0;
+    let mut depth = 0;
+
+    fs::create_dir_all(root)?;
+    while let Some((dir, level)) = pending.pop_front() {
+        for index in 0..fanout {
+            if created == count {
+                return Ok(depth);
+            }
+            let child = dir.join(format!("d{:02}", index));
+            fs::create_dir(&child)?;
+            //@ A file per directory so listings are not trivially empty
+            fs::write(child.join("tool"), "")?;
+            created += 1;
+            depth = level + 1;
+            pending.push_back((child, level + 1));
+        }
+    }
+    Ok(depth)
+}
@@ -0,0 +1,219 @@
+use crate::{
+    config::{backup, exclude, layer, migrate},
+    utilities::{diff_lines, write_atomic},
+    Config,
+};
+use clap::Subcommand;
+use std::{error::Error, fs, path::Path};
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Validate the config file without modifying it
+    Check,
+
+    /// Print the effective config in this shell, merged from every layer
+    Show {
+        /// Name the file or environment variable each setting came from
+        #[arg(long)]
+        origin: bool,
+    },
+
+    /// Upgrade the config to the current schema version
+    Migrate {
+        /// Show the changes without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Roll the config back to a backup taken before an earlier save
+    Restore {
+        /// Backup to restore, by number from --list or file name (default: the newest)
+        backup: Option<String>,
+
+        /// List the available backups, newest first
+        #[arg(long)]
+        list: bool,
+    },
+}
+
+impl Commands {
+    /// Runs before the config is loaded, so a broken file can still be inspected
+    pub fn execute(&self, custom_config_path: Option<&Path>) -> Result<(), Box<dyn Error>> {
+        match self {
+            Self::Check => self.check(custom_config_path),
+            Self::Show { origin } => self.show(custom_config_path, *origin),
+            Self::Migrate { dry_run } => self.migrate(custom_config_path, *dry_run),
+            Self::Restore { backup, list } => {
+                if *list {
+                    self.list_backups(custom_config_path)
+                } else {
+                    self.restore(custom_config_path, backup.as_deref())
+                }
+            }
+        }
+    }
+
+    fn check(&self, custom_config_path: Option<&Path>) -> Result<(), Box<dyn Error>> {
+        let config_path = Config::locate(custom_config_path);
+        let content = fs::read_to_string(&config_path)
+            .map_err(|err| format!("Could not read {}: {}", config_path.display(), err))?;
+        let (_, changes) = Config::parse_versioned(&config_path, &content)?;
+
+        //@ Then the other layers, which the patterns below may come from
+        let (config, _) = Config::load(&config_path, layer::Scope::Session)?;
+
+        //@ Patterns only compile when scanning, so try them here too
+        for entry in &config.path_entries {
+            exclude::Rules::new(
+                &entry.path,
+                &entry.exclude_patterns,
+                &config.default_excludes,
+                entry.bins_only,
+            )
+            .map_err(|err| format!("{}: {}", entry.path.display(), err))?;
+        }
+
+        println!(
+            "{} is valid: {} path entries, {} variables",
+            config_path.display(),
+            config.path_entries.len(),
+            config.variables.len()
+        );
+        if !changes.is_empty() {
+            println!(
+                "It predates version {}; run `dotsrus config migrate --dry-run` to see the upgrade",
+                migrate::CURRENT_VERSION
+            );
+        }
+        Ok(())
+    }
+
+    fn show(&self, custom_config_path: Option<&Path>, origin: bool) -> Result<(), Box<dyn Error>> {
+        let config_path = Config::locate(custom_config_path);
+        let (config, _) = Config::load(&config_path, layer::Scope::Session)?;
+        if let Some(project) = layer::disallowed_project(&config_path) {
+            eprintln!(
+                "Leaving out {}, which is not allowed; run `dotsrus allow` to apply it",
+                project.display()
+            );
+        }
+        let effective = config.effective_table()?;
+        if !origin {
+            print!("{}", toml::to_string_pretty(&effective)?);
+            return Ok(());
+        }
+
+        for (key, value) in layer::flatten(effective) {
+            if key != "version" {
+                println!("{} = {}  # {}", key, value, config.layers.origin(&key));
+            }
+        }
+        Ok(())
+    }
+
+    fn migrate(
+        &self,
+        custom_config_path: Option<&Path>,
+        dry_run: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let config_path = Config::locate(custom_config_path);
+        let content = fs::read_to_string(&config_path)
+            .map_err(|err| format!("Could not read {}: {}", config_path.display(), err))?;
+        let (mut config, changes) = Config::load(&config_path, layer::Scope::Stored)?;
+        if changes.is_empty() {
+            println!(
+                "{} is already at version {}",
+                config_path.display(),
+                migrate::CURRENT_VERSION
+            );
+            return Ok(());
+        }
+
+        for change in &changes {
+            println!("- {}", change);
+        }
+        println!("--- {}", config_path.display());
+        print!("{}", diff_lines(&content, &config.user_content()?));
+        if dry_run {
+            println!("Dry run, nothing was changed");
+            return Ok(());
+        }
+
+        config.load_state();
+        config.save()?;
+        println!(
+            "Upgraded {} to version {}; the previous file is in {}",
+            config_path.display(),
+            migrate::CURRENT_VERSION,
+            backup::dir(&config_path).display()
+        );
+        Ok(())
+    }
+
+    fn list_backups(&self, custom_config_path: Option<&Path>) -> Result<(), Box<dyn Error>> {
+        let config_path = Config::locate(custom_config_path);
+        let backups = backup::list(&config_path)?;
+        if backups.is_empty() {
+            println!("No backups of {}", config_path.display());
+            return Ok(());
+        }
+
+        for (number, backup_path) in backups.iter().enumerate() {
+            println!("{:>3}  {}", number + 1, backup_path.display());
+        }
+        Ok(())
+    }
+
+    fn restore(
+        &self,
+        custom_config_path: Option<&Path>,
+        selector: Option<&str>,
+    ) -> Result<(), Box<dyn Error>> {
+        let config_path = Config::locate(custom_config_path);
+        let backups = backup::list(&config_path)?;
+
+        //@ Accept the number shown by --list, or the backup's file name
+        let backup_path = match selector {
+            None => backups.first(),
+            Some(selector) => match selector.parse::<usize>() {
+                Ok(number) => number.checked_sub(1).and_then(|i| backups.get(i)),
+                Err(_) => backups.iter().find(|path| {
+                    path.file_name()
+                        .is_some_and(|name| name.to_string_lossy() == selector)
+                }),
+            },
+        }
+        .ok_or_else(|| match selector {
+            None => format!("No backups of {}", config_path.display()),
+            Some(selector) => format!(
+                "No backup matches '{}'; see `config restore --list`",
+                selector
+            ),
+        })?;
+
+        let content = fs::read_to_string(backup_path)
+            .map_err(|err| format!("Could not read {}: {}", backup_path.display(), err))?;
+        Config::parse(&config_path, &content)?;
+
+        //@ Back up the current file too, so the restore itself can be undone
+        if let Ok(current) = fs::read_to_string(&config_path) {
+            if current != content && !current.trim().is_empty() {
+                backup::create(&config_path, &current)?;
+            }
+        }
+        write_atomic(&config_path, &content)?;
+
+        //@ Regenerate from the restored file merged with the layers around it
+        let (mut config, _) = Config::load(&config_path, layer::Scope::Stored)?;
+        config.load_state();
+        config.write_scripts()?;
+        config.write_cache()?;
+
+        println!(
+            "Restored {} from {}",
+            config_path.display(),
+            backup_path.display()
+        );
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use super::path;
+use super::{allow, bench, config, env, hook, path, variable, watch, which};
 use clap::Subcommand;
 
 #[derive(Subcommand)]
@@ -10,45 +10,39 @@ pub enum Commands {
     },
 
     /// Set and manage environment variables
-    Set {
-        /// Variable name
-        name: String,
+    Set(variable::Set),
 
-        /// Variable value
-        value: Option<String>,
-
-        /// Add a prefix to the variable name
-        #[arg(long)]
-        prefix: Option<String>,
+    /// Show environment variables
+    Show(variable::Show),
 
-        /// Add a suffix to the variable name
-        #[arg(long)]
-        suffix: Option<String>,
+    /// Print the environment for a shell to `eval` at startup
+    Env(env::Env),
 
-        /// Export the variable globally
-        #[arg(long)]
-        export: bool,
+    /// Show which copy of a command runs, and which copies it shadows
+    Which(which::Which),
 
-        /// Cache the variable for persistence
-        #[arg(long)]
-        cache: bool,
+    /// Keep discovered directories and scripts in sync as files change
+    Watch(watch::Watch),
 
-        /// Set as a path variable with path validation
-        #[arg(long)]
-        path: bool,
+    /// Apply a directory's .dotsrus.toml in `env`, as it reads now
+    Allow(allow::Allow),
 
-        /// Set as a command/executable with PATH lookup
-        #[arg(long)]
-        command: bool,
+    /// Inspect and maintain the config file
+    Config {
+        #[command(subcommand)]
+        action: config::Commands,
     },
 
-    /// Show environment variables
-    Show {
-        /// Variable pattern to search for
-        pattern: Option<String>,
+    /// Install or remove the shell startup hook
+    Hook {
+        #[command(subcommand)]
+        action: hook::Commands,
+    },
 
-        /// Show raw output without formatting
-        #[arg(long)]
-        raw: bool,
+    /// Benchmark scanning and startup over synthetic trees
+    #[command(hide = true)]
+    Bench {
+        #[command(subcommand)]
+        action: bench::Commands,
     },
 }
@@ -0,0 +1,67 @@
+use crate::{
+    config::{cache, layer},
+    shell::Shell,
+    Config,
+};
+use clap::Args;
+use std::{error::Error, path::Path};
+
+#[derive(Args)]
+pub struct Env {
+    /// Shell to render for (default: detected from $SHELL)
+    #[arg(long, value_enum)]
+    shell: Option<Shell>,
+}
+
+impl Env {
+    /// Print from the cache if the config and its directories are unchanged; returns whether it did
+    pub fn execute_cached(&self, custom_config_path: Option<&Path>) -> bool {
+        let config_path = Config::locate(custom_config_path);
+        //@ The cache only holds the stored layers
+        if layer::session_differs(&config_path) {
+            return false;
+        }
+        let Ok(config_hash) = layer::checksum(&config_path) else {
+            return false;
+        };
+
+        //@ A name that fails to render is reported by the uncached path
+        let script = cache::Cache::read(&cache::path(&config_path))
+            .filter(|cache| cache.is_fresh(config_hash))
+            .and_then(|cache| {
+                self.shell()
+                    .render_resolved(&cache.prepend, &cache.append, &cache.variables)
+                    .ok()
+            });
+        match script {
+            Some(script) => {
+                print!("{}", script);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn execute(&self, config: &mut Config) -> Result<(), Box<dyn Error>> {
+        //@ The cache was stale or missing: pick up directory changes, then rebuild what derives
+        //@ from the config, scripts included, since a hand edit may have changed variables
+        config.refresh_discovered(false, 0)?;
+        config.write_state()?;
+        config.write_scripts()?;
+        config.write_cache()?;
+
+        //@ What only this shell sees goes into the output, never into the files above
+        if layer::session_differs(&config.config_path) {
+            let mut session = config.session()?;
+            session.refresh_discovered(false, 0)?;
+            print!("{}", self.shell().render(&session)?);
+        } else {
+            print!("{}", self.shell().render(config)?);
+        }
+        Ok(())
+    }
+
+    fn shell(&self) -> Shell {
+        self.shell.unwrap_or_else(Shell::detect)
+    }
+}
@@ -0,0 +1,207 @@
+use crate::{
+    shell::Shell,
+    utilities::{diff_lines, write_atomic, FileLock},
+    Config,
+};
+use clap::Subcommand;
+use directories::BaseDirs;
+use std::{
+    error::Error,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+const BLOCK_START: &str = "# >>> dotsrus >>>";
+const BLOCK_END: &str = "# <<< dotsrus <<<";
+const LEGACY_TAG: &str = "#| Dots 'R' Us";
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Load the generated environment from shell rc files
+    Install {
+        /// Shells to hook (comma-separated, default: detected from $SHELL)
+        #[arg(long, value_enum, value_delimiter = ',')]
+        shell: Vec<Shell>,
+
+        /// Use this rc file instead of the shell's default
+        #[arg(long)]
+        file: Option<PathBuf>,
+
+        /// Apply the changes without asking for confirmation
+        #[arg(long, short)]
+        yes: bool,
+
+        /// Only show the changes that would be made
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Remove the dotsrus block from shell rc files
+    Uninstall {
+        /// Shells to unhook (comma-separated, default: detected from $SHELL)
+        #[arg(long, value_enum, value_delimiter = ',')]
+        shell: Vec<Shell>,
+
+        /// Use this rc file instead of the shell's default
+        #[arg(long)]
+        file: Option<PathBuf>,
+
+        /// Apply the changes without asking for confirmation
+        #[arg(long, short)]
+        yes: bool,
+
+        /// Only show the changes that would be made
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+impl Commands {
+    /// Update the rc files, holding the config `lock` only while writing the scripts
+    pub fn execute(&self, config: &Config, lock: FileLock) -> Result<(), Box<dyn Error>> {
+        let (shells, file, yes, dry_run, install) = match self {
+            Self::Install {
+                shell,
+                file,
+                yes,
+                dry_run,
+            } => (shell, file, *yes, *dry_run, true),
+            Self::Uninstall {
+                shell,
+                file,
+                yes,
+                dry_run,
+            } => (shell, file, *yes, *dry_run, false),
+        };
+
+        let targets = Self::targets(shells, file.as_deref())?;
+
+        //@ Make sure there is something to source before hooking it up
+        if install && !dry_run {
+            config.write_scripts()?;
+        }
+        //@ Nothing else comes from the config, and the prompt may wait indefinitely
+        drop(lock);
+
+        let mut changes = Vec::new();
+        for (shell, rc_path) in targets {
+            let old = if rc_path.exists() {
+                fs::read_to_string(&rc_path)?
+            } else {
+                String::new()
+            };
+            let new = if install {
+                Self::with_block(&old, &shell.source(&config.script_path(shell)))
+            } else {
+                Self::without_block(&old)
+            };
+
+            if old == new {
+                println!("{}: nothing to change", rc_path.display());
+                continue;
+            }
+
+            println!("--- {}", rc_path.display());
+            print!("{}", diff_lines(&old, &new));
+            changes.push((rc_path, new));
+        }
+
+        if changes.is_empty() || dry_run {
+            return Ok(());
+        }
+
+        if !yes && !Self::confirm("Apply these changes?")? {
+            println!("Aborted, no files were changed");
+            return Ok(());
+        }
+
+        for (rc_path, content) in changes {
+            if let Some(parent) = rc_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            write_atomic(&rc_path, content)?;
+            println!("Updated {}", rc_path.display());
+        }
+        Ok(())
+    }
+
+    fn targets(
+        shells: &[Shell],
+        file: Option<&Path>,
+    ) -> Result<Vec<(Shell, PathBuf)>, Box<dyn Error>> {
+        let shells = if shells.is_empty() {
+            vec![Shell::detect()]
+        } else {
+            shells.to_vec()
+        };
+
+        if let Some(file) = file {
+            return match shells.as_slice() {
+                [shell] => Ok(vec![(*shell, file.to_path_buf())]),
+                _ => Err("--file can only be used with a single --shell".into()),
+            };
+        }
+
+        let base_dir = BaseDirs::new().ok_or("Failed to get base directories")?;
+        Ok(shells
+            .into_iter()
+            .map(|shell| (shell, shell.rc_file(base_dir.home_dir())))
+            .collect())
+    }
+
+    //@ Replace any existing block with a fresh one at the end of the file
+    fn with_block(content: &str, source_line: &str) -> String {
+        let mut content = Self::without_block(content);
+        if !content.is_empty() && !content.ends_with("\n\n") {
+            if !content.ends_with('\n') {
+                content.push('\n');
+            }
+            content.push('\n');
+        }
+        content.push_str(&format!("{BLOCK_START}\n{source_line}\n{BLOCK_END}\n"));
+        content
+    }
+
+    //@ Drop the marker block, and the two-line tag written by older versions
+    fn without_block(content: &str) -> String {
+        let mut kept: Vec<&str> = Vec::new();
+        let mut lines = content.lines();
+
+        while let Some(line) = lines.next() {
+            match line.trim() {
+                BLOCK_START => {
+                    for line in lines.by_ref() {
+                        if line.trim() == BLOCK_END {
+                            break;
+                        }
+                    }
+                }
+                LEGACY_TAG => {
+                    lines.next();
+                }
+                _ => kept.push(line),
+            }
+        }
+
+        //@ Don't leave the blank separator line behind
+        while kept.last().is_some_and(|line| line.trim().is_empty()) {
+            kept.pop();
+        }
+
+        if kept.is_empty() {
+            String::new()
+        } else {
+            format!("{}\n", kept.join("\n"))
+        }
+    }
+
+    fn confirm(prompt: &str) -> io::Result<bool> {
+        print!("{prompt} [y/N] ");
+        io::stdout().flush()?;
+
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+    }
+}
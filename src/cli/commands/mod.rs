@@ -1,2 +1,10 @@
+pub mod allow;
+pub mod bench;
+pub mod config;
 pub mod default;
+pub mod env;
+pub mod hook;
 pub mod path;
+pub mod variable;
+pub mod watch;
+pub mod which;
@@ -1,15 +1,17 @@
 use crate::{
-    config::{entry, exclude},
-    utilities::component_matches_pattern,
+    config::{discover, entry, exclude, layer, lookup, variable},
+    utilities::{is_executable, split_patterns},
     Config,
 };
 use clap::Subcommand;
-use ignore::WalkBuilder;
+use indexmap::IndexMap;
 use std::{
     collections::{HashMap, HashSet},
+    env,
     error::Error,
+    ffi::OsString,
     fs,
-    path::{Component, Path, PathBuf},
+    path::{Path, PathBuf},
 };
 
 #[derive(Subcommand)]
@@ -23,7 +25,11 @@ pub enum Commands {
         #[arg(long)]
         prepend: bool,
 
-        /// Exclude patterns (comma-separated)
+        /// Higher priority entries come first within their side of PATH
+        #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+        priority: i32,
+
+        /// Exclude patterns (comma-separated globs, `re:` for regex, `!` to re-include)
         #[arg(long)]
         exclude: Option<String>,
 
@@ -34,6 +40,30 @@ pub enum Commands {
         /// Maximum recursion depth (default: 5)
         #[arg(long, default_value_t = 5)]
         max_depth: u8,
+
+        /// Minimum depth of discovered directories; above 0 the root itself is left out
+        #[arg(long, default_value_t = 0)]
+        min_depth: u8,
+
+        /// Follow symbolic links while scanning
+        #[arg(long)]
+        follow_symlinks: bool,
+
+        /// Order of discovered directories in PATH
+        #[arg(long, value_enum, default_value_t = discover::Order::DepthFirst)]
+        order: discover::Order,
+
+        /// Only add discovered directories that contain executables
+        #[arg(long)]
+        bins_only: bool,
+
+        /// Rescan existing entries completely instead of only changed directories
+        #[arg(long)]
+        full: bool,
+
+        /// Threads per directory walk (default: chosen automatically)
+        #[arg(long, short, default_value_t = 0)]
+        jobs: usize,
     },
 
     /// Remove directory from PATH
@@ -50,10 +80,59 @@ pub enum Commands {
         /// Show raw output without formatting
         #[arg(long)]
         raw: bool,
+
+        /// Show the final ordered PATH, flagging duplicates and shadowed commands
+        #[arg(long)]
+        effective: bool,
+    },
+
+    /// Rescan entries, revisiting only directories changed since the last scan
+    Refresh {
+        /// Rescan every directory, e.g. after editing exclude patterns by hand
+        #[arg(long)]
+        full: bool,
+
+        /// Threads per directory walk (default: chosen automatically)
+        #[arg(long, short, default_value_t = 0)]
+        jobs: usize,
     },
 
     /// Reset discovered paths and rescan
-    Reset,
+    Reset {
+        /// Threads per directory walk (default: chosen automatically)
+        #[arg(long, short, default_value_t = 0)]
+        jobs: usize,
+    },
+
+    /// Move an entry relative to another, or change its priority
+    Move {
+        /// Directory to move
+        path: PathBuf,
+
+        /// Place it right before this entry
+        #[arg(long, conflicts_with = "after")]
+        before: Option<PathBuf>,
+
+        /// Place it right after this entry
+        #[arg(long)]
+        after: Option<PathBuf>,
+
+        /// Set the priority of the entry
+        #[arg(long, allow_negative_numbers = true)]
+        priority: Option<i32>,
+    },
+
+    /// Show the order entries are emitted in
+    Order,
+
+    /// List commands provided by more than one managed directory
+    Conflicts,
+
+    /// Explain which rule includes or excludes a directory
+    Why {
+        /// Directory to explain
+        path: PathBuf,
+    },
 }
 
 //@ Update Self implementation to use Config
@@ -63,19 +142,32 @@ impl Commands {
             Self::Add {
                 paths,
                 prepend,
+                priority,
                 exclude,
                 make_exec,
                 max_depth,
+                min_depth,
+                follow_symlinks,
+                order,
+                bins_only,
+                full,
+                jobs,
             } => {
-                self.add_to_path(
-                    paths,
-                    *prepend,
-                    exclude.as_deref(),
-                    *make_exec,
-                    *max_depth,
-                    config,
-                )?;
-                self.refresh_recursive_paths(config)?;
+                let settings = entry::Path {
+                    path: PathBuf::new(),
+                    prepend: *prepend,
+                    priority: *priority,
+                    exclude_patterns: exclude.as_deref().map(split_patterns).unwrap_or_default(),
+                    max_depth: *max_depth,
+                    min_depth: *min_depth,
+                    follow_symlinks: *follow_symlinks,
+                    order: *order,
+                    bins_only: *bins_only,
+                    discovered_paths: IndexMap::new(),
+                    rules_hash: 0,
+                };
+                self.add_to_path(paths, &settings, *make_exec, config)?;
+                config.refresh_discovered(*full, *jobs)?;
             }
             Self::Remove { paths } => {
                 self.remove_from_path(paths, config)?;
@@ -83,15 +175,37 @@ impl Commands {
             Self::Clean => {
                 self.clean_path(config)?;
             }
-            Self::Show { raw } => {
-                self.show_path(*raw, config)?;
+            //@ Reports change nothing, so they return without saving
+            Self::Show { raw, effective } => {
+                return if *effective {
+                    self.show_effective_path(*raw, config)
+                } else {
+                    self.show_path(*raw, config)
+                };
+            }
+            Self::Refresh { full, jobs } => {
+                config.refresh_discovered(*full, *jobs)?;
             }
-            Self::Reset => {
+            Self::Reset { jobs } => {
                 for entry in &mut config.path_entries {
                     entry.discovered_paths.clear();
                 }
-                self.refresh_recursive_paths(config)?;
+                config.refresh_discovered(true, *jobs)?;
             }
+            Self::Move {
+                path,
+                before,
+                after,
+                priority,
+            } => {
+                self.move_entry(path, before.as_deref(), after.as_deref(), *priority, config)?;
+            }
+            Self::Order => {
+                self.show_order(config);
+                return Ok(());
+            }
+            Self::Conflicts => return self.show_conflicts(config),
+            Self::Why { path } => return self.explain(path, config),
         }
         config.save()?;
         Ok(())
@@ -100,10 +214,8 @@ impl Commands {
     fn add_to_path(
         &self,
         paths: &[PathBuf],
-        prepend: bool,
-        exclude: Option<&str>,
+        settings: &entry::Path,
         make_exec: bool,
-        max_depth: u8,
         config: &mut Config,
     ) -> Result<(), Box<dyn Error>> {
         for path in paths {
@@ -117,28 +229,14 @@ impl Commands {
                 Self::make_scripts_executable(&canonical_path)?;
             }
 
-            let exclude_patterns = exclude
-                .map(|e| e.split(',').map(String::from).collect())
-                .unwrap_or_default();
-
-            let mut entry = entry::Path {
+            let entry = entry::Path {
                 path: canonical_path.clone(),
-                prepend,
-                exclude_patterns,
-                max_depth,
-                discovered_paths: HashMap::new(),
+                ..settings.clone()
             };
 
-            //@ Only scan if max_depth > 1
-            if max_depth > 1 {
-                let patterns = entry.exclude_patterns.clone();
-                let default_excludes = config.default_excludes.clone();
-                entry.discovered_paths =
-                    self.scan_directory(&canonical_path, &patterns, &default_excludes, max_depth);
-            }
-
+            //@ New entries start empty, so the refresh that follows scans them in full
             if !config.path_entries.iter().any(|e| e.path == entry.path) {
-                if prepend {
+                if entry.prepend {
                     config.path_entries.insert(0, entry);
                 } else {
                     config.path_entries.push(entry);
@@ -148,143 +246,174 @@ impl Commands {
         Ok(())
     }
 
-    fn refresh_recursive_paths(&self, config: &mut Config) -> Result<(), Box<dyn Error>> {
-        // Clone the default excludes before the loop
-        let default_excludes = config.default_excludes.clone();
-
-        for entry in &mut config.path_entries {
-            if entry.max_depth > 1 {
-                let root_path = entry.path.clone();
-                let patterns = entry.exclude_patterns.clone();
+    fn move_entry(
+        &self,
+        path: &Path,
+        before: Option<&Path>,
+        after: Option<&Path>,
+        priority: Option<i32>,
+        config: &mut Config,
+    ) -> Result<(), Box<dyn Error>> {
+        let index = Self::entry_index(path, config)?;
+        let mut entry = config.path_entries.remove(index);
+
+        let anchor = before.map(|p| (p, true)).or(after.map(|p| (p, false)));
+        let position = match anchor {
+            Some((anchor, is_before)) => {
+                let anchor_index = match Self::entry_index(anchor, config) {
+                    Ok(anchor_index) => anchor_index,
+                    Err(err) => {
+                        config.path_entries.insert(index, entry);
+                        return Err(err);
+                    }
+                };
+
+                //@ Join the anchor's side and priority so the new position sticks
+                let target = &config.path_entries[anchor_index];
+                if entry.prepend != target.prepend {
+                    println!(
+                        "{} is now {}",
+                        entry.path.display(),
+                        if target.prepend {
+                            "prepended"
+                        } else {
+                            "appended"
+                        }
+                    );
+                }
+                entry.prepend = target.prepend;
+                entry.priority = target.priority;
 
-                // Pass default_excludes instead of the whole config
-                let discovered = self.scan_directory(&root_path, &patterns, &default_excludes, 5);
-                entry.discovered_paths = discovered;
+                if is_before {
+                    anchor_index
+                } else {
+                    anchor_index + 1
+                }
             }
+            None => index,
+        };
+
+        if let Some(priority) = priority {
+            entry.priority = priority;
         }
+        config.path_entries.insert(position, entry);
         Ok(())
     }
 
-    fn scan_directory(
-        &self,
-        path: &Path,
-        exclude_patterns: &[String],
-        default_excludes: &[String],
-        max_depth: u8,
-    ) -> HashMap<PathBuf, exclude::Check> {
-        let mut discovered = HashMap::new();
-
-        if !path.exists() {
-            discovered.insert(
-                path.to_path_buf(),
-                exclude::Check::new_invalid(exclude::Reason::DoesNotExist),
-            );
-            return discovered;
-        }
-
-        if !path.is_dir() {
-            discovered.insert(
-                path.to_path_buf(),
-                exclude::Check::new_invalid(exclude::Reason::NotDirectory),
-            );
-            return discovered;
-        }
-
-        let walker = WalkBuilder::new(path)
-            .hidden(false)
-            .git_ignore(true)
-            .ignore(true)
-            .max_depth(Some(max_depth as usize))
-            .build();
-
-        for result in walker {
-            match result {
-                Ok(dir_entry) => {
-                    let path = dir_entry.path();
-
-                    if !path.is_dir() {
-                        continue;
-                    }
+    fn entry_index(path: &Path, config: &Config) -> Result<usize, Box<dyn Error>> {
+        let canonical_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        config
+            .path_entries
+            .iter()
+            .position(|entry| entry.path == canonical_path)
+            .ok_or_else(|| format!("{} is not a PATH entry", path.display()).into())
+    }
 
-                    // Get directory components
-                    let components: Vec<_> = path
-                        .components()
-                        .filter_map(|comp| match comp {
-                            Component::Normal(os_str) => os_str.to_str().map(|s| s.to_lowercase()),
-                            _ => None,
-                        })
-                        .collect();
-
-                    // Check if any component exactly matches our patterns
-                    let is_excluded = components.iter().any(|component| {
-                        exclude_patterns
-                            .iter()
-                            .any(|pattern| component_matches_pattern(component, pattern))
-                            || default_excludes
-                                .iter()
-                                .any(|pattern| component_matches_pattern(component, pattern))
-                    });
-
-                    if is_excluded {
-                        // Find which pattern matched for better error reporting
-                        let matching_pattern = exclude_patterns
-                            .iter()
-                            .find(|pattern| {
-                                components
-                                    .iter()
-                                    .any(|comp| component_matches_pattern(comp, pattern))
-                            })
-                            .cloned()
-                            .map(exclude::Reason::ExcludePattern)
-                            .or_else(|| {
-                                default_excludes
-                                    .iter()
-                                    .find(|pattern| {
-                                        components
-                                            .iter()
-                                            .any(|comp| component_matches_pattern(comp, pattern))
-                                    })
-                                    .cloned()
-                                    .map(exclude::Reason::DefaultExclude)
-                            });
-
-                        discovered.insert(
-                            path.to_path_buf(),
-                            exclude::Check::new_invalid(matching_pattern.unwrap_or(
-                                exclude::Reason::Other("Unknown pattern match".to_string()),
-                            )),
-                        );
-                        continue;
-                    }
+    fn show_conflicts(&self, config: &Config) -> Result<(), Box<dyn Error>> {
+        let index = lookup::Index::load(config)?;
+        let mut found = false;
 
-                    // Check git ignore rules
-                    if components.iter().any(|comp| comp == ".git") {
-                        discovered.insert(
-                            path.to_path_buf(),
-                            exclude::Check::new_invalid(exclude::Reason::GitIgnored),
-                        );
-                        continue;
-                    }
-
-                    // If we get here, the path is valid
-                    discovered.insert(path.to_path_buf(), exclude::Check::new_valid());
+        for (name, dirs) in index.conflicts() {
+            found = true;
+            println!("{}", name);
+            for (position, dir) in dirs.iter().enumerate() {
+                if position == 0 {
+                    println!("  {}", dir.join(name).display());
+                } else {
+                    println!("  {} (shadowed)", dir.join(name).display());
                 }
-                Err(err) => {
-                    let reason = match err.io_error() {
-                        Some(io_err) => match io_err.kind() {
-                            std::io::ErrorKind::PermissionDenied => {
-                                exclude::Reason::PermissionDenied
-                            }
-                            _ => exclude::Reason::Other(err.to_string()),
-                        },
-                        None => exclude::Reason::Other(err.to_string()),
-                    };
+            }
+        }
 
-                    discovered.insert(path.to_path_buf(), exclude::Check::new_invalid(reason));
-                }
+        if !found {
+            println!("No command is provided by more than one managed directory");
+        }
+        Ok(())
+    }
+
+    fn show_order(&self, config: &Config) {
+        let entries = config.ordered_entries();
+        let width = entries
+            .iter()
+            .map(|entry| entry.priority.to_string().len())
+            .max()
+            .unwrap_or(1);
+
+        for (label, prepend) in [("Before $PATH:", true), ("After $PATH:", false)] {
+            println!("{}", label);
+            for entry in entries.iter().filter(|entry| entry.prepend == prepend) {
+                println!("  [{:>width$}] {}", entry.priority, entry.path.display());
             }
         }
-        discovered
+    }
+
+    fn explain(&self, path: &Path, config: &Config) -> Result<(), Box<dyn Error>> {
+        let canonical_path = path.canonicalize()?;
+
+        //@ The innermost entry containing the directory is the one that scans it
+        let entry = config
+            .path_entries
+            .iter()
+            .filter(|entry| canonical_path.starts_with(&entry.path))
+            .max_by_key(|entry| entry.path.components().count())
+            .ok_or_else(|| format!("{} is not under any PATH entry", path.display()))?;
+
+        println!("{}", canonical_path.display());
+        println!("  entry:  {}", entry.path.display());
+
+        let depth = canonical_path
+            .strip_prefix(&entry.path)?
+            .components()
+            .count();
+        let (result, rule) = if depth == 0 && entry.min_depth == 0 && !entry.root_qualifies() {
+            (
+                format!("excluded ({:?})", exclude::Reason::NoExecutables),
+                "holds no executables (bins-only)".to_string(),
+            )
+        } else if depth == 0 && entry.min_depth == 0 {
+            (
+                "included".to_string(),
+                "entry roots are added when min depth is 0".to_string(),
+            )
+        } else if entry.max_depth <= 1 || depth > entry.max_depth as usize {
+            (
+                "excluded".to_string(),
+                format!("deeper than the entry's max depth of {}", entry.max_depth),
+            )
+        } else if depth < entry.min_depth as usize {
+            (
+                "excluded".to_string(),
+                format!(
+                    "shallower than the entry's min depth of {}",
+                    entry.min_depth
+                ),
+            )
+        } else {
+            let rules = exclude::Rules::new(
+                &entry.path,
+                &entry.exclude_patterns,
+                &config.default_excludes,
+                entry.bins_only,
+            )?;
+            let decision = rules.decide(&canonical_path);
+            let result = match decision.excluded {
+                Some(reason) => format!("excluded ({:?})", reason),
+                None => "included".to_string(),
+            };
+            (result, decision.rule)
+        };
+        println!("  result: {}", result);
+        println!("  rule:   {}", rule);
+
+        let stored = match entry.discovered_paths.get(&canonical_path) {
+            Some(check) if check.valid => "valid".to_string(),
+            Some(check) => format!("invalid ({:?})", check.invalid_reason.as_ref().unwrap()),
+            None if depth == 0 => "not scanned".to_string(),
+            None => "not discovered (ignored by a .gitignore/.ignore file, or not scanned yet)"
+                .to_string(),
+        };
+        println!("  stored: {}", stored);
+        Ok(())
     }
 
     // Modified show_path implementation to display invalid paths
@@ -305,9 +434,11 @@ impl Commands {
             println!("Configured PATH entries:");
             for entry in &config.path_entries {
                 println!(
-                    "{} (prepend: {}, recursive: {}, exclude: {:?})",
+                    "{} (prepend: {}, priority: {}, depth: {}..={}, exclude: {:?})",
                     entry.path.display(),
                     entry.prepend,
+                    entry.priority,
+                    entry.min_depth,
                     entry.max_depth,
                     entry.exclude_patterns
                 );
@@ -340,6 +471,82 @@ impl Commands {
         Ok(())
     }
 
+    fn show_effective_path(&self, raw: bool, config: &Config) -> Result<(), Box<dyn Error>> {
+        let inherited = env::var_os("PATH")
+            .map(|path| env::split_paths(&path).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let path = config.effective_path(&inherited);
+
+        if raw {
+            for dir in &path {
+                println!("{}", dir.display());
+            }
+            return Ok(());
+        }
+
+        let (prepend, append) = config.path_directories();
+        let mut first_seen: HashMap<&PathBuf, usize> = HashMap::new();
+        let mut providers: HashMap<OsString, usize> = HashMap::new();
+
+        println!("Effective PATH:");
+        for (index, dir) in path.iter().enumerate() {
+            let mut flags = if prepend.contains(dir) {
+                vec!["prepended".to_string()]
+            } else if !inherited.contains(dir) {
+                vec!["appended".to_string()]
+            } else if append.contains(dir) {
+                vec![
+                    "inherited".to_string(),
+                    "managed, already on PATH".to_string(),
+                ]
+            } else {
+                vec!["inherited".to_string()]
+            };
+
+            if let Some(first) = first_seen.get(dir) {
+                flags.push(format!("duplicate of {}", first + 1));
+            } else {
+                first_seen.insert(dir, index);
+
+                //@ Commands in this directory that an earlier one already provides
+                let mut shadowed: Vec<String> = Self::executables(dir)
+                    .into_iter()
+                    .filter(|name| providers.entry(name.clone()).or_insert(index) != &index)
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .collect();
+                shadowed.sort();
+                if !shadowed.is_empty() {
+                    let count = shadowed.len();
+                    shadowed.truncate(5);
+                    let mut names = shadowed.join(", ");
+                    if count > 5 {
+                        names.push_str(&format!(" and {} more", count - 5));
+                    }
+                    flags.push(format!("shadowed: {}", names));
+                }
+            }
+
+            if !dir.is_dir() {
+                flags.push("missing".to_string());
+            }
+
+            println!("{:>3}  {} [{}]", index + 1, dir.display(), flags.join("; "));
+        }
+        Ok(())
+    }
+
+    fn executables(dir: &Path) -> Vec<OsString> {
+        fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|entry| is_executable(&entry.path()))
+                    .map(|entry| entry.file_name())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     fn remove_from_path(
         &self,
         paths: &[PathBuf],
@@ -348,14 +555,46 @@ impl Commands {
         let paths_to_remove: HashSet<_> =
             paths.iter().filter_map(|p| p.canonicalize().ok()).collect();
 
-        config
-            .path_entries
-            .retain(|entry| !paths_to_remove.contains(&entry.path));
+        config.path_entries.retain(|entry| {
+            if !paths_to_remove.contains(&entry.path) {
+                return true;
+            }
+
+            //@ Only the user config is written, so entries from other layers come back
+            let key = layer::entry_key(&entry.path.to_string_lossy());
+            if let Some(origin) = config.layers.inherited_from(&key) {
+                println!(
+                    "{} is set in {}, so it stays until removed there",
+                    entry.path.display(),
+                    origin
+                );
+            }
+            false
+        });
         Ok(())
     }
 
     fn clean_path(&self, config: &mut Config) -> Result<(), Box<dyn Error>> {
-        config.path_entries.retain(|entry| entry.path.exists());
+        config.path_entries.retain(|entry| {
+            let exists = entry.path.exists();
+            if !exists {
+                println!("Removed missing PATH entry: {}", entry.path.display());
+            }
+            exists
+        });
+
+        //@ Path-valued variables whose targets have vanished
+        config.variables.retain(|variable| {
+            let vanished =
+                variable.kind == variable::Kind::Path && !Path::new(&variable.value).exists();
+            if vanished {
+                println!(
+                    "Removed variable with missing target: {} ({})",
+                    variable.name, variable.value
+                );
+            }
+            !vanished
+        });
         Ok(())
     }
 
@@ -0,0 +1,373 @@
+use crate::{
+    config::{entry, variable},
+    utilities::{
+        component_matches_pattern, expand_home, find_executable, is_executable,
+        shell_quote_if_needed, Case, KeyNormalizer,
+    },
+    Config,
+};
+use clap::{Args, ValueEnum};
+use std::{
+    collections::{BTreeMap, HashSet},
+    env,
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Target {
+    /// The path must be a directory
+    Dir,
+    /// The path must be a regular file
+    File,
+}
+
+#[derive(Args)]
+pub struct Set {
+    /// Variable name
+    name: String,
+
+    /// Variable value
+    value: Option<String>,
+
+    /// Add a prefix to the variable name
+    #[arg(long)]
+    prefix: Option<String>,
+
+    /// Add a suffix to the variable name
+    #[arg(long)]
+    suffix: Option<String>,
+
+    /// Delimiter used to join the prefix, name and suffix
+    #[arg(long, default_value = "_")]
+    delimiter: String,
+
+    /// Force the case of the variable name
+    #[arg(long, value_enum, default_value_t = Case::Preserve)]
+    case: Case,
+
+    /// Export the variable globally
+    #[arg(long)]
+    export: bool,
+
+    /// Deprecated: every variable persists, so this does nothing
+    #[arg(long, hide = true)]
+    cache: bool,
+
+    /// Set as a path variable with path validation
+    #[arg(long, conflicts_with = "command")]
+    path: bool,
+
+    /// Set as a command/executable with PATH lookup
+    #[arg(long)]
+    command: bool,
+
+    /// Create the path if it doesn't exist (default: refuse)
+    #[arg(long, requires = "path")]
+    create: bool,
+
+    /// Require the path to be a directory or a file
+    #[arg(long, value_enum, requires = "path")]
+    expect: Option<Target>,
+
+    /// Show the computed variable without saving it
+    #[arg(long)]
+    dry_run: bool,
+}
+
+impl Set {
+    pub fn execute(&self, config: &mut Config) -> Result<(), Box<dyn Error>> {
+        if self.cache {
+            eprintln!("Warning: --cache is deprecated and does nothing; every variable persists");
+        }
+        let name = self.name()?;
+
+        //@ Without a value, report the current definition instead
+        let Some(value) = &self.value else {
+            if self.dry_run {
+                println!("{}", name);
+                return Ok(());
+            }
+            return self.print_current(&name, config);
+        };
+
+        let variable = self.resolve(name, value, config)?;
+
+        if self.dry_run {
+            println!(
+                "{}={}",
+                variable.name,
+                shell_quote_if_needed(&variable.value)
+            );
+            return Ok(());
+        }
+
+        config.set_variable(variable);
+        config.save()?;
+        Ok(())
+    }
+
+    fn resolve(
+        &self,
+        name: String,
+        value: &str,
+        config: &Config,
+    ) -> Result<entry::Variable, Box<dyn Error>> {
+        let mut variable = entry::Variable {
+            name,
+            value: value.to_string(),
+            original: None,
+            kind: self.kind(),
+            scope: self.scope(),
+            lookup_order: Vec::new(),
+        };
+
+        match variable.kind {
+            variable::Kind::Command => {
+                let lookup_order = Self::command_lookup_order(config);
+                variable.value = Self::resolve_command(value, &lookup_order)?
+                    .display()
+                    .to_string();
+                variable.original = Some(value.to_string());
+                variable.lookup_order = lookup_order;
+            }
+            variable::Kind::Path => {
+                variable.value = self.resolve_path(value)?.display().to_string();
+                variable.original = Some(value.to_string());
+            }
+            variable::Kind::Plain => {}
+        }
+
+        Ok(variable)
+    }
+
+    fn resolve_path(&self, value: &str) -> Result<PathBuf, Box<dyn Error>> {
+        let path = expand_home(value);
+
+        if !path.exists() {
+            if !self.create {
+                return Err(format!(
+                    "'{}' does not exist (use --create to create it)",
+                    path.display()
+                )
+                .into());
+            }
+
+            //@ Report what would be created without touching the filesystem
+            if self.dry_run {
+                return Ok(path);
+            }
+
+            if self.expect == Some(Target::File) {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::File::create(&path)?;
+            } else {
+                fs::create_dir_all(&path)?;
+            }
+        }
+
+        let canonical_path = path.canonicalize()?;
+
+        match self.expect {
+            Some(Target::Dir) if !canonical_path.is_dir() => {
+                Err(format!("'{}' is not a directory", canonical_path.display()).into())
+            }
+            Some(Target::File) if !canonical_path.is_file() => {
+                Err(format!("'{}' is not a file", canonical_path.display()).into())
+            }
+            _ => Ok(canonical_path),
+        }
+    }
+
+    //@ Search in the order the shell will once the generated script has run
+    fn command_lookup_order(config: &Config) -> Vec<PathBuf> {
+        let inherited = env::var_os("PATH")
+            .map(|path| env::split_paths(&path).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        let mut seen = HashSet::new();
+        config
+            .effective_path(&inherited)
+            .into_iter()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .filter(|dir| seen.insert(dir.clone()))
+            .collect()
+    }
+
+    fn resolve_command(command: &str, lookup_order: &[PathBuf]) -> Result<PathBuf, Box<dyn Error>> {
+        //@ Explicit paths skip the lookup but must still be executable
+        if command.contains(std::path::MAIN_SEPARATOR) || command.contains('/') {
+            let path = Path::new(command);
+            if !is_executable(path) {
+                return Err(format!("'{}' is not an executable file", command).into());
+            }
+            return Ok(path.canonicalize()?);
+        }
+
+        match find_executable(command, lookup_order) {
+            Some(path) => Ok(path),
+            None => {
+                let searched = lookup_order
+                    .iter()
+                    .map(|dir| format!("  {}", dir.display()))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                Err(format!(
+                    "Command '{}' not found. Searched directories:\n{}",
+                    command, searched
+                )
+                .into())
+            }
+        }
+    }
+
+    fn name(&self) -> Result<String, Box<dyn Error>> {
+        let normalizer = KeyNormalizer {
+            delimiter: self.delimiter.clone(),
+            case: self.case,
+            prefix: self.prefix.clone(),
+            suffix: self.suffix.clone(),
+        };
+        Ok(normalizer.normalize(&[&self.name])?)
+    }
+
+    fn kind(&self) -> variable::Kind {
+        if self.path {
+            variable::Kind::Path
+        } else if self.command {
+            variable::Kind::Command
+        } else {
+            variable::Kind::Plain
+        }
+    }
+
+    fn scope(&self) -> variable::Scope {
+        if self.export {
+            variable::Scope::Export
+        } else {
+            variable::Scope::Local
+        }
+    }
+
+    fn print_current(&self, name: &str, config: &Config) -> Result<(), Box<dyn Error>> {
+        if let Some(variable) = config.get_variable(name) {
+            println!("{}={}", variable.name, variable.value);
+        } else if let Ok(value) = env::var(name) {
+            println!("{}={}", name, value);
+        } else {
+            return Err(format!("{} is not set", name).into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+pub struct Show {
+    /// Variable pattern to search for
+    pattern: Option<String>,
+
+    /// Show raw output without formatting
+    #[arg(long)]
+    raw: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Origin {
+    Managed,    // Set by dotsrus and live value agrees
+    Inherited,  // Only present in the process environment
+    Overridden, // Set by dotsrus but the live value differs
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Self::Managed => "managed",
+            Self::Inherited => "inherited",
+            Self::Overridden => "overridden",
+        };
+        f.pad(label)
+    }
+}
+
+struct Listing<'a> {
+    value: String,
+    origin: Origin,
+    managed: Option<&'a entry::Variable>,
+}
+
+impl Show {
+    pub fn execute(&self, config: &Config) -> Result<(), Box<dyn Error>> {
+        let listings = self.collect(config);
+
+        if self.raw {
+            for (name, listing) in &listings {
+                println!("{}={}", name, shell_quote_if_needed(&listing.value));
+            }
+            return Ok(());
+        }
+
+        if listings.is_empty() {
+            println!("No matching variables");
+            return Ok(());
+        }
+
+        let width = listings.keys().map(String::len).max().unwrap_or(0);
+        for (name, listing) in &listings {
+            print!(
+                "[{:<10}] {:<width$} = {}",
+                listing.origin, name, listing.value
+            );
+            if let (Origin::Overridden, Some(managed)) = (listing.origin, listing.managed) {
+                print!(" (managed: {})", managed.value);
+            }
+            println!();
+        }
+        Ok(())
+    }
+
+    fn collect<'a>(&self, config: &'a Config) -> BTreeMap<String, Listing<'a>> {
+        let mut listings = BTreeMap::new();
+
+        //@ Start with the live process environment
+        for (name, value) in env::vars_os() {
+            let name = name.to_string_lossy().into_owned();
+            if self.matches(&name) {
+                let listing = Listing {
+                    value: value.to_string_lossy().into_owned(),
+                    origin: Origin::Inherited,
+                    managed: None,
+                };
+                listings.insert(name, listing);
+            }
+        }
+
+        //@ Merge in the variables dotsrus manages
+        for variable in config.variables.iter().filter(|v| self.matches(&v.name)) {
+            let listing = listings
+                .entry(variable.name.clone())
+                .or_insert_with(|| Listing {
+                    value: variable.value.clone(),
+                    origin: Origin::Managed,
+                    managed: None,
+                });
+            listing.origin = if listing.value == variable.value {
+                Origin::Managed
+            } else {
+                Origin::Overridden
+            };
+            listing.managed = Some(variable);
+        }
+
+        listings
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match &self.pattern {
+            Some(pattern) => component_matches_pattern(name, &pattern.to_lowercase()),
+            None => true,
+        }
+    }
+}
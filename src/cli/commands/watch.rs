@@ -0,0 +1,239 @@
+use crate::{config::cache, Config};
+use clap::Args;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError},
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+#[derive(Args)]
+pub struct Watch {
+    /// Quiet time in milliseconds before a burst of changes is applied
+    #[arg(long, default_value_t = 300)]
+    debounce: u64,
+
+    /// Print a systemd user unit that runs the watcher, then exit
+    #[arg(long)]
+    unit: bool,
+}
+
+impl Watch {
+    pub fn execute(&self, config: &mut Config) -> Result<(), Box<dyn Error>> {
+        if self.unit {
+            print!("{}", self.unit_file(config)?);
+            return Ok(());
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        let mut watched = HashSet::new();
+
+        //@ Watch the config's directory, since saves may replace the file rather than write it
+        let config_dir = config
+            .config_path
+            .parent()
+            .ok_or("Config path has no parent directory")?
+            .to_path_buf();
+        watcher.watch(&config_dir, RecursiveMode::NonRecursive)?;
+        let mut config_hash = file_hash(&config.config_path);
+
+        sync_watches(&mut watcher, &mut watched, config);
+        println!(
+            "Watching {} entries and {}",
+            config.path_entries.len(),
+            config.config_path.display()
+        );
+
+        let debounce = Duration::from_millis(self.debounce);
+        while let Ok(first) = receiver.recv() {
+            if !is_change(&first) {
+                continue;
+            }
+
+            //@ Collect changes until the burst goes quiet; reads alone do not extend it
+            let mut events = vec![first];
+            let mut quiet_at = Instant::now() + debounce;
+            loop {
+                let remaining = quiet_at.saturating_duration_since(Instant::now());
+                match receiver.recv_timeout(remaining) {
+                    Ok(event) if is_change(&event) => {
+                        events.push(event);
+                        quiet_at = Instant::now() + debounce;
+                    }
+                    Ok(_) => {}
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return Ok(()),
+                }
+            }
+
+            let mut changed_paths = Vec::new();
+            for event in events {
+                match event {
+                    Ok(event) => changed_paths.extend(event.paths),
+                    Err(err) => eprintln!("Watch error: {}", err),
+                }
+            }
+
+            let _lock = match Config::lock(Some(&config.config_path)) {
+                Ok(lock) => lock,
+                Err(err) => {
+                    eprintln!("Skipping update: {}", err);
+                    continue;
+                }
+            };
+
+            //@ Reload when the config was edited elsewhere; our own saves leave the hash as recorded
+            let config_edited = changed_paths.contains(&config.config_path)
+                && file_hash(&config.config_path) != config_hash;
+            if config_edited {
+                match Config::new(Some(&config.config_path)) {
+                    Ok(reloaded) => *config = reloaded,
+                    Err(err) => {
+                        eprintln!("Keeping the previous config: {}", err);
+                        continue;
+                    }
+                }
+                println!("Reloaded {}", config.config_path.display());
+            }
+
+            let tree_changed = changed_paths.iter().any(|path| {
+                config
+                    .path_entries
+                    .iter()
+                    .any(|entry| path.starts_with(&entry.path))
+            });
+            if !config_edited && !tree_changed {
+                continue;
+            }
+
+            if let Err(err) = self.update(config, &changed_paths, config_edited) {
+                eprintln!("Error updating discovered paths: {}", err);
+                continue;
+            }
+            config_hash = file_hash(&config.config_path);
+            sync_watches(&mut watcher, &mut watched, config);
+        }
+        Ok(())
+    }
+
+    /// Rescan what changed, writing the state and scripts if PATH is affected
+    fn update(
+        &self,
+        config: &mut Config,
+        changed_paths: &[PathBuf],
+        config_edited: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        //@ Expire the checks of changed directories and their parents, so they are read again
+        //@ even when their mtime did not move, e.g. a file made executable
+        for entry in &mut config.path_entries {
+            for path in changed_paths {
+                for dir in [Some(path.as_path()), path.parent()].into_iter().flatten() {
+                    if let Some(check) = entry.discovered_paths.get_mut(dir) {
+                        check.timestamp = UNIX_EPOCH;
+                    }
+                }
+            }
+        }
+
+        //@ An edit may change the rules, which stored checks know nothing about
+        let before = discovered(config);
+        let directories = config.path_directories();
+        config.refresh_discovered(config_edited, 0)?;
+
+        let after = discovered(config);
+        if after != before || config.path_directories() != directories {
+            let valid = |set: &HashMap<PathBuf, bool>| -> HashSet<PathBuf> {
+                set.iter()
+                    .filter(|(_, valid)| **valid)
+                    .map(|(path, _)| path.clone())
+                    .collect()
+            };
+            let (before, after) = (valid(&before), valid(&after));
+            let added = after.difference(&before).count();
+            let removed = before.difference(&after).count();
+            config.write_state()?;
+            config.write_scripts()?;
+            config.write_cache()?;
+            println!(
+                "Updated PATH: {} directories added, {} removed",
+                added, removed
+            );
+        } else if config_edited {
+            //@ Variables may have changed; regenerate without rewriting the edited file
+            config.write_scripts()?;
+            config.write_cache()?;
+        }
+        Ok(())
+    }
+
+    fn unit_file(&self, config: &Config) -> Result<String, Box<dyn Error>> {
+        let exe = env::current_exe()?;
+        Ok(format!(
+            "[Unit]\n\
+             Description=Keep dotsrus PATH entries in sync\n\
+             \n\
+             [Service]\n\
+             ExecStart=\"{}\" --config \"{}\" watch --debounce {}\n\
+             Restart=on-failure\n\
+             \n\
+             [Install]\n\
+             WantedBy=default.target\n",
+            exe.display(),
+            config.config_path.display(),
+            self.debounce
+        ))
+    }
+}
+
+/// Discovered directories across all entries, and whether each is valid
+fn discovered(config: &Config) -> HashMap<PathBuf, bool> {
+    config
+        .path_entries
+        .iter()
+        .flat_map(|entry| &entry.discovered_paths)
+        .map(|(path, check)| (path.clone(), check.valid))
+        .collect()
+}
+
+/// Watch each entry root and discovered directory, dropping those that went away
+fn sync_watches(watcher: &mut RecommendedWatcher, watched: &mut HashSet<PathBuf>, config: &Config) {
+    //@ One level per directory, so max depth also bounds the number of watches
+    let wanted: HashSet<PathBuf> = config
+        .path_entries
+        .iter()
+        .flat_map(|entry| std::iter::once(&entry.path).chain(entry.discovered_paths.keys()))
+        .filter(|path| path.is_dir())
+        .cloned()
+        .collect();
+
+    for path in watched.difference(&wanted) {
+        let _ = watcher.unwatch(path);
+    }
+    watched.retain(|path| wanted.contains(path));
+
+    for path in wanted {
+        if !watched.contains(&path) && watcher.watch(&path, RecursiveMode::NonRecursive).is_ok() {
+            watched.insert(path);
+        }
+    }
+}
+
+/// Anything but a read, which our own rescans and saves also cause
+fn is_change(event: &notify::Result<Event>) -> bool {
+    !matches!(
+        event,
+        Ok(Event {
+            kind: EventKind::Access(_),
+            ..
+        })
+    )
+}
+
+fn file_hash(path: &Path) -> Option<u64> {
+    fs::read(path).ok().map(|content| cache::checksum(&content))
+}
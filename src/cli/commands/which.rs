@@ -0,0 +1,60 @@
+use crate::{config::lookup, utilities::is_executable, Config};
+use clap::Args;
+use std::{collections::HashSet, env, error::Error, path::PathBuf};
+
+#[derive(Args)]
+pub struct Which {
+    /// Command to look up
+    name: String,
+
+    /// Show every copy on PATH, not only the one that runs
+    #[arg(long, short)]
+    all: bool,
+}
+
+impl Which {
+    pub fn execute(&self, config: &Config) -> Result<(), Box<dyn Error>> {
+        let index = lookup::Index::load(config)?;
+        let managed_dirs: HashSet<_> = index.dirs().collect();
+        let providers: HashSet<_> = index.lookup(&self.name).into_iter().collect();
+
+        let inherited: Vec<PathBuf> = env::var_os("PATH")
+            .map(|path| env::split_paths(&path).collect())
+            .unwrap_or_default();
+
+        //@ Walk the effective PATH so inherited copies are ranked against managed ones
+        let mut seen = HashSet::new();
+        let mut copies = Vec::new();
+        for dir in config.effective_path(&inherited) {
+            if !seen.insert(dir.clone()) {
+                continue;
+            }
+            let candidate = dir.join(&self.name);
+            if managed_dirs.contains(dir.as_path()) {
+                if providers.contains(dir.as_path()) {
+                    copies.push((candidate, "managed"));
+                }
+            } else if is_executable(&candidate) {
+                copies.push((candidate, "inherited"));
+            }
+        }
+
+        if copies.is_empty() {
+            return Err(format!("{} not found on PATH", self.name).into());
+        }
+
+        if !self.all {
+            println!("{}", copies[0].0.display());
+            return Ok(());
+        }
+
+        for (position, (path, origin)) in copies.iter().enumerate() {
+            if position == 0 {
+                println!("{} ({})", path.display(), origin);
+            } else {
+                println!("{} ({}, shadowed)", path.display(), origin);
+            }
+        }
+        Ok(())
+    }
+}
@@ -17,8 +17,48 @@ pub struct Cli {
 pub fn init() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
 
+    //@ `env` runs at every shell startup, so try the cache before parsing any TOML
+    if let Commands::Env(env) = &cli.command {
+        if env.execute_cached(cli.config.as_deref()) {
+            return Ok(());
+        }
+    }
+
+    //@ Benchmarks only touch synthetic configs, so the user's is neither loaded nor locked
+    if let Commands::Bench { action } = &cli.command {
+        if let Err(err) = action.execute() {
+            eprintln!("Error running benchmark: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    //@ Hold the lock from reading the config until every file derived from it is written
+    let lock = match Config::lock(cli.config.as_deref()) {
+        Ok(lock) => lock,
+        Err(err) => {
+            eprintln!("Error locking config: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    //@ Config maintenance must work even when the file does not parse
+    if let Commands::Config { action } = &cli.command {
+        if let Err(err) = action.execute(cli.config.as_deref()) {
+            eprintln!("Error executing config command: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     // Initialize config
-    let mut config = Config::new(cli.config.as_deref())?;
+    let mut config = match Config::new(cli.config.as_deref()) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Error loading config: {}", err);
+            std::process::exit(1);
+        }
+    };
 
     match cli.command {
         Commands::Path { action } => {
@@ -27,23 +67,52 @@ pub fn init() -> Result<(), Box<dyn Error>> {
                 std::process::exit(1);
             }
         }
-        Commands::Set {
-            name,
-            value,
-            prefix,
-            suffix,
-            export,
-            cache,
-            path,
-            command,
-        } => {
-            println!("Setting variable: {}", name);
-            // TODO: Implement variable setting logic
+        Commands::Set(set) => {
+            if let Err(err) = set.execute(&mut config) {
+                eprintln!("Error setting variable: {}", err);
+                std::process::exit(1);
+            }
         }
-        Commands::Show { pattern, raw } => {
-            println!("Showing environment variables");
-            // TODO: Implement environment variable display logic
+        Commands::Show(show) => {
+            if let Err(err) = show.execute(&config) {
+                eprintln!("Error showing variables: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Commands::Env(env) => {
+            if let Err(err) = env.execute(&mut config) {
+                eprintln!("Error rendering environment: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Commands::Which(which) => {
+            if let Err(err) = which.execute(&config) {
+                eprintln!("Error looking up command: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Commands::Watch(watch) => {
+            //@ The watcher runs indefinitely, so it locks around each update instead
+            drop(lock);
+            if let Err(err) = watch.execute(&mut config) {
+                eprintln!("Error watching for changes: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Commands::Allow(allow) => {
+            if let Err(err) = allow.execute(&config) {
+                eprintln!("Error allowing project config: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Commands::Config { .. } => unreachable!("handled before loading the config"),
+        Commands::Hook { action } => {
+            if let Err(err) = action.execute(&config, lock) {
+                eprintln!("Error executing hook command: {}", err);
+                std::process::exit(1);
+            }
         }
+        Commands::Bench { .. } => unreachable!("handled before loading the config"),
     }
 
     Ok(())
@@ -1,3 +1,6 @@
 mod modules;
 
-pub use modules::{config::Config, entry, exclude};
+pub use modules::{
+    backup, cache, config::Config, discover, entry, exclude, layer, lookup, migrate, state, trust,
+    variable,
+};
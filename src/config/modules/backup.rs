@@ -0,0 +1,141 @@
+use super::state;
+use crate::utilities::write_atomic;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Backups kept per config; older ones are removed as new ones are taken
+pub const MAX_BACKUPS: usize = 10;
+
+/// Directory holding the backups of the config at `config_path`
+pub fn dir(config_path: &Path) -> PathBuf {
+    state::dir(config_path).join("backups")
+}
+
+/// Where older versions kept backups, next to the config
+fn legacy_dir(config_path: &Path) -> PathBuf {
+    config_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join("backups")
+}
+
+/// Store `content` as the newest backup of the config at `config_path`, pruning the oldest
+pub fn create(config_path: &Path, content: &str) -> io::Result<PathBuf> {
+    let dir = dir(config_path);
+    fs::create_dir_all(&dir)?;
+
+    let backup_path = dir.join(format!("{}-{}.toml", stem(config_path), timestamp()));
+    write_atomic(&backup_path, content)?;
+
+    for stale in list(config_path)?.into_iter().skip(MAX_BACKUPS) {
+        fs::remove_file(stale)?;
+    }
+    Ok(backup_path)
+}
+
+/// Backups of the config at `config_path`, newest first, including those older versions
+/// kept next to it
+pub fn list(config_path: &Path) -> io::Result<Vec<PathBuf>> {
+    let prefix = format!("{}-", stem(config_path));
+    let mut backups = Vec::new();
+    for dir in [dir(config_path), legacy_dir(config_path)] {
+        let read_dir = match fs::read_dir(dir) {
+            Ok(read_dir) => read_dir,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        backups.extend(
+            read_dir
+                .flatten()
+                .map(|dir_entry| dir_entry.path())
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(|name| name.starts_with(&prefix) && name.ends_with(".toml"))
+                }),
+        );
+    }
+
+    //@ Names embed a zero-padded UTC timestamp, so they sort chronologically
+    backups.sort_by(|a, b| b.file_name().cmp(&a.file_name()));
+    Ok(backups)
+}
+
+fn stem(config_path: &Path) -> String {
+    config_path.file_stem().map_or_else(
+        || "config".into(),
+        |stem| stem.to_string_lossy().into_owned(),
+    )
+}
+
+/// Current UTC time as `YYYYMMDD-HHMMSS-mmm`
+fn timestamp() -> String {
+    let since = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = since.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let time = secs % 86_400;
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        since.subsec_millis()
+    )
+}
+
+/// Gregorian date of a day count since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    //@ Shift to eras of 400 years starting in March, so leap days fall at the end of a year
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn days_map_to_gregorian_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+        assert_eq!(
+            civil_from_days(47_541),
+            (2100, 3, 1),
+            "2100 is not a leap year"
+        );
+    }
+
+    #[test]
+    fn timestamps_sort_in_the_order_they_were_taken() {
+        let first = timestamp();
+        thread::sleep(Duration::from_millis(2));
+        let second = timestamp();
+        assert_eq!(first.len(), "YYYYMMDD-HHMMSS-mmm".len());
+        assert!(first < second, "{} < {}", first, second);
+    }
+}
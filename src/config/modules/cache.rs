@@ -0,0 +1,338 @@
+use super::{config::Config, entry, state, variable};
+use crate::utilities::write_atomic;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const MAGIC: &[u8; 4] = b"DRUS";
+const VERSION: u16 = 1;
+
+/// Source directories with their mtimes when the data built from them was resolved
+pub type Sources = Vec<(PathBuf, Option<SystemTime>)>;
+
+/// Resolved PATH and variables, with what is needed to tell when they go stale
+#[derive(Debug)]
+pub struct Cache {
+    pub config_hash: u64,
+    pub sources: Sources,
+    pub prepend: Vec<PathBuf>,
+    pub append: Vec<PathBuf>,
+    pub variables: Vec<entry::Variable>,
+}
+
+impl Cache {
+    /// Resolve `config`, whose file content hashes to `config_hash`
+    pub fn build(config: &Config, config_hash: u64) -> Self {
+        //@ Entry roots and scanned directories: a new or removed subdirectory moves their mtime
+        let mut sources = Vec::new();
+        for entry in &config.path_entries {
+            sources.push(entry.path.clone());
+            sources.extend(entry.discovered_paths.keys().cloned());
+        }
+        sources.sort();
+        sources.dedup();
+
+        let (prepend, append) = config.path_directories();
+        Self {
+            config_hash,
+            sources: stamp(sources),
+            prepend,
+            append,
+            variables: config.variables.clone(),
+        }
+    }
+
+    /// Whether the config is unchanged and every source directory looks as it did
+    pub fn is_fresh(&self, config_hash: u64) -> bool {
+        self.config_hash == config_hash && unchanged(&self.sources)
+    }
+
+    pub fn read(path: &Path) -> Option<Self> {
+        Self::decode(&fs::read(path).ok()?)
+    }
+
+    /// Write the cache, or remove a stale one when it cannot be encoded
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        write_or_remove(path, self.encode())
+    }
+
+    /// `None` when a path is not UTF-8
+    pub fn encode(&self) -> Option<Vec<u8>> {
+        let mut payload = Vec::new();
+        payload.extend(self.config_hash.to_le_bytes());
+
+        put_sources(&mut payload, &self.sources)?;
+
+        for dirs in [&self.prepend, &self.append] {
+            put_len(&mut payload, dirs.len());
+            for dir in dirs {
+                put_str(&mut payload, dir.to_str()?);
+            }
+        }
+
+        put_len(&mut payload, self.variables.len());
+        for variable in &self.variables {
+            put_str(&mut payload, &variable.name);
+            put_str(&mut payload, &variable.value);
+            payload.push(match variable.scope {
+                variable::Scope::Local => 0,
+                variable::Scope::Export => 1,
+            });
+        }
+
+        Some(seal(MAGIC, VERSION, payload))
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut reader = open(MAGIC, VERSION, bytes)?;
+        let config_hash = reader.u64()?;
+        let sources = read_sources(&mut reader)?;
+
+        let mut dirs = [Vec::new(), Vec::new()];
+        for side in &mut dirs {
+            for _ in 0..reader.u32()? {
+                side.push(PathBuf::from(reader.str()?));
+            }
+        }
+        let [prepend, append] = dirs;
+
+        let mut variables = Vec::new();
+        for _ in 0..reader.u32()? {
+            let name = reader.str()?.to_string();
+            let value = reader.str()?.to_string();
+            let scope = match reader.u8()? {
+                0 => variable::Scope::Local,
+                _ => variable::Scope::Export,
+            };
+            variables.push(entry::Variable {
+                name,
+                value,
+                original: None,
+                kind: variable::Kind::default(),
+                scope,
+                lookup_order: Vec::new(),
+            });
+        }
+
+        Some(Self {
+            config_hash,
+            sources,
+            prepend,
+            append,
+            variables,
+        })
+    }
+}
+
+/// Location of the cache kept for the config at `config_path`
+pub fn path(config_path: &Path) -> PathBuf {
+    state::dir(config_path).join("env.cache")
+}
+
+/// FNV-1a, used for both the config hash and the cache checksum
+pub fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Record the current mtime of each path
+pub(super) fn stamp(paths: Vec<PathBuf>) -> Sources {
+    paths
+        .into_iter()
+        .map(|path| {
+            let modified = modified(&path);
+            (path, modified)
+        })
+        .collect()
+}
+
+/// Whether every path still has the mtime it was stamped with
+pub(super) fn unchanged(sources: &Sources) -> bool {
+    sources
+        .iter()
+        .all(|(path, recorded)| modified(path) == *recorded)
+}
+
+/// Write encoded bytes, or remove a stale file when there is nothing to write
+pub(super) fn write_or_remove(path: &Path, bytes: Option<Vec<u8>>) -> io::Result<()> {
+    match bytes {
+        Some(bytes) => {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            write_atomic(path, bytes)
+        }
+        None => match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        },
+    }
+}
+
+/// Frame a payload with magic, version and the payload's checksum
+pub(super) fn seal(magic: &[u8; 4], version: u16, payload: Vec<u8>) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(magic.len() + 2 + 8 + payload.len());
+    bytes.extend(magic);
+    bytes.extend(version.to_le_bytes());
+    bytes.extend(checksum(&payload).to_le_bytes());
+    bytes.extend(payload);
+    bytes
+}
+
+/// Check a frame from `seal`, rejecting other versions and corrupt or truncated files
+pub(super) fn open<'a>(magic: &[u8; 4], version: u16, bytes: &'a [u8]) -> Option<Reader<'a>> {
+    let mut reader = Reader(bytes);
+    if reader.take(magic.len())? != magic || reader.u16()? != version {
+        return None;
+    }
+    let expected = reader.u64()?;
+    (checksum(reader.0) == expected).then_some(reader)
+}
+
+pub(super) fn put_len(bytes: &mut Vec<u8>, len: usize) {
+    bytes.extend((len as u32).to_le_bytes());
+}
+
+pub(super) fn put_str(bytes: &mut Vec<u8>, value: &str) {
+    put_len(bytes, value.len());
+    bytes.extend(value.as_bytes());
+}
+
+/// `None` when a path is not UTF-8
+pub(super) fn put_sources(bytes: &mut Vec<u8>, sources: &Sources) -> Option<()> {
+    put_len(bytes, sources.len());
+    for (path, modified) in sources {
+        put_str(bytes, path.to_str()?);
+        match modified.and_then(|time| time.duration_since(UNIX_EPOCH).ok()) {
+            Some(since) => {
+                bytes.push(1);
+                bytes.extend(since.as_secs().to_le_bytes());
+                bytes.extend(since.subsec_nanos().to_le_bytes());
+            }
+            None => bytes.push(0),
+        }
+    }
+    Some(())
+}
+
+pub(super) fn read_sources(reader: &mut Reader) -> Option<Sources> {
+    let mut sources = Vec::new();
+    for _ in 0..reader.u32()? {
+        let path = PathBuf::from(reader.str()?);
+        let modified = match reader.u8()? {
+            0 => None,
+            _ => Some(UNIX_EPOCH + Duration::new(reader.u64()?, reader.u32()?)),
+        };
+        sources.push((path, modified));
+    }
+    Some(sources)
+}
+
+pub(super) struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(head)
+    }
+
+    pub(super) fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    pub(super) fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    pub(super) fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    pub(super) fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    pub(super) fn str(&mut self) -> Option<&'a str> {
+        let len = self.u32()? as usize;
+        std::str::from_utf8(self.take(len)?).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Cache {
+        Cache {
+            config_hash: 0x0123_4567_89ab_cdef,
+            sources: vec![
+                (
+                    PathBuf::from("/opt/tools"),
+                    Some(UNIX_EPOCH + Duration::new(1_700_000_000, 42)),
+                ),
+                (PathBuf::from("/gone"), None),
+            ],
+            prepend: vec![PathBuf::from("/opt/tools/bin")],
+            append: vec![
+                PathBuf::from("/opt/tools"),
+                PathBuf::from("/opt/tools/sbin"),
+            ],
+            variables: vec![entry::Variable {
+                name: "EDITOR".to_string(),
+                value: "nvim 'x'".to_string(),
+                original: None,
+                kind: variable::Kind::Plain,
+                scope: variable::Scope::Export,
+                lookup_order: Vec::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn encoding_round_trips() {
+        let cache = sample();
+        let decoded = Cache::decode(&cache.encode().unwrap()).unwrap();
+        assert_eq!(decoded.config_hash, cache.config_hash);
+        assert_eq!(decoded.sources, cache.sources);
+        assert_eq!(decoded.prepend, cache.prepend);
+        assert_eq!(decoded.append, cache.append);
+        assert_eq!(decoded.variables.len(), 1);
+        assert_eq!(decoded.variables[0].name, "EDITOR");
+        assert_eq!(decoded.variables[0].value, "nvim 'x'");
+        assert_eq!(decoded.variables[0].scope, variable::Scope::Export);
+    }
+
+    #[test]
+    fn corrupt_truncated_or_foreign_files_are_rejected() {
+        let bytes = sample().encode().unwrap();
+
+        //@ Flip a bit in the payload, after magic, version and checksum
+        let mut corrupt = bytes.clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        assert!(Cache::decode(&corrupt).is_none());
+
+        let mut checksum = bytes.clone();
+        checksum[6] ^= 1;
+        assert!(Cache::decode(&checksum).is_none());
+
+        assert!(Cache::decode(&bytes[..bytes.len() - 1]).is_none());
+
+        let mut version = bytes.clone();
+        version[4] = version[4].wrapping_add(1);
+        assert!(Cache::decode(&version).is_none());
+        assert!(Cache::decode(b"").is_none());
+    }
+}
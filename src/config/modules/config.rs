@@ -1,26 +1,67 @@
+use super::cache::{self, Cache};
 use super::entry;
-use super::exclude;
-use directories::{BaseDirs, ProjectDirs};
-use ignore::WalkBuilder;
+use super::{backup, discover, exclude, layer, migrate, state};
+use crate::shell::Shell;
+use crate::utilities::{is_valid_name, write_atomic, FileLock};
+use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashSet,
     error::Error,
     fs::{self, File},
-    io::{self, Write},
-    path::{Path, PathBuf, MAIN_SEPARATOR, MAIN_SEPARATOR_STR},
+    io,
+    path::{Path, PathBuf},
+    thread,
 };
+use thiserror::Error;
 
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Could not access {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("{} is invalid at line {line}, column {column}: {message}", path.display())]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("Refusing to overwrite {}, which no longer parses; run `dotsrus config check`", path.display())]
+    Invalid { path: PathBuf },
+    #[error("{} is version {version}, newer than the {} this dotsrus understands; upgrade dotsrus", path.display(), migrate::CURRENT_VERSION)]
+    Unsupported { path: PathBuf, version: u32 },
+    #[error("Could not upgrade {}: {message}", path.display())]
+    Upgrade { path: PathBuf, message: String },
+    #[error("Could not merge config layers: {message}")]
+    Layers { message: String },
+    #[error("{origin} sets variable {name:?}, which is not a valid shell variable name")]
+    VariableName { origin: String, name: String },
+}
+
+/// Unknown keys are refused, so a misspelled setting is not silently dropped on the next save
 #[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
+    #[serde(default = "migrate::current_version")]
+    pub version: u32,
+    #[serde(default)]
     pub path_entries: Vec<entry::Path>,
+    /// Written by version 1 files, still accepted so they can be upgraded
+    #[serde(default, skip_serializing)]
     pub config_path: PathBuf,
     #[serde(default = "exclude::default")]
     pub default_excludes: Vec<String>,
+    #[serde(default)]
+    pub variables: Vec<entry::Variable>,
+    #[serde(skip)]
+    pub layers: layer::Layers,
 }
 
 impl Config {
-    pub fn new(custom_config_path: Option<&Path>) -> io::Result<Self> {
-        let config_path = if let Some(path) = custom_config_path {
+    /// Location of the config file, without reading it. Always absolute, with its directory
+    /// canonicalized, since state, caches and the lock are all keyed on it
+    pub fn locate(custom_config_path: Option<&Path>) -> PathBuf {
+        let path = if let Some(path) = custom_config_path {
             path.to_path_buf()
         } else {
             let proj_dirs = ProjectDirs::from("com", "ccutils", env!("CARGO_PKG_NAME"))
@@ -28,175 +69,362 @@ impl Config {
             proj_dirs.config_dir().join("config.toml")
         };
 
+        //@ The file name is kept, so a symlinked config still has its scripts written beside it
+        let path = std::path::absolute(&path).unwrap_or(path);
+        match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => fs::canonicalize(parent)
+                .map(|parent| parent.join(name))
+                .unwrap_or(path),
+            _ => path,
+        }
+    }
+
+    /// Take the lock serializing read-modify-write cycles on the config at `custom_config_path`
+    pub fn lock(custom_config_path: Option<&Path>) -> Result<FileLock, ConfigError> {
+        let lock_path = state::dir(&Self::locate(custom_config_path)).join("config.lock");
+        FileLock::acquire(&lock_path).map_err(|source| ConfigError::Io {
+            path: lock_path,
+            source,
+        })
+    }
+
+    pub fn new(custom_config_path: Option<&Path>) -> Result<Self, ConfigError> {
+        let config_path = Self::locate(custom_config_path);
+        let io_error = |source| ConfigError::Io {
+            path: config_path.clone(),
+            source,
+        };
+
         if !config_path.exists() {
             if let Some(parent) = config_path.parent() {
-                fs::create_dir_all(parent)?;
+                fs::create_dir_all(parent).map_err(io_error)?;
             }
-            File::create(&config_path)?;
-            Ok(Config {
-                path_entries: Vec::new(),
-                config_path,
-                default_excludes: exclude::default().into_iter().collect(),
-            })
-        } else {
-            let content = fs::read_to_string(&config_path)?;
-            let mut config: Config = toml::from_str(&content).unwrap_or_default();
-            config.config_path = config_path;
-            Ok(config)
+            File::create(&config_path).map_err(io_error)?;
         }
-    }
 
-    pub fn save(&self) -> Result<(), Box<dyn Error>> {
-        let content = toml::to_string_pretty(self)?;
-        fs::write(&self.config_path, content)?;
-        self.update_shell_profile()?;
-        Ok(())
+        let (mut config, changes) = Self::load(&config_path, layer::Scope::Stored)?;
+        config.load_state();
+
+        //@ Upgrade older files in place; saving backs up the previous content first
+        if !changes.is_empty() {
+            config.save().map_err(|err| ConfigError::Upgrade {
+                path: config_path.clone(),
+                message: err.to_string(),
+            })?;
+            eprintln!(
+                "Upgraded {} to version {}; the previous file is in {}",
+                config_path.display(),
+                migrate::CURRENT_VERSION,
+                backup::dir(&config_path).display()
+            );
+        }
+        Ok(config)
     }
 
-    fn update_shell_profile(&self) -> Result<(), Box<dyn Error>> {
-        let base_dir = BaseDirs::new().expect("Failed to get base directories");
-        let home_dir = base_dir.home_dir().to_path_buf();
-        let profile_tag = "#| Dots 'R' Us";
-        let profile_path = home_dir.join(".profile");
-        let script_path = self.config_path.with_extension("env");
+    /// Read the config at `config_path` merged with the layers of `scope`, writing nothing.
+    /// Also returns the changes needed to bring the file itself up to date
+    pub fn load(
+        config_path: &Path,
+        scope: layer::Scope,
+    ) -> Result<(Self, Vec<String>), ConfigError> {
+        let content = fs::read_to_string(config_path).map_err(|source| ConfigError::Io {
+            path: config_path.to_path_buf(),
+            source,
+        })?;
+        let (_, table, changes) = Self::parse_layer(config_path, &content)?;
+        Ok((layer::load(config_path, table, scope)?, changes))
+    }
 
-        //@ Create .profile if it doesn't exist
-        if !profile_path.exists() {
-            fs::File::create(&profile_path)?;
+    /// This config as one shell sees it, with an allowed project config and environment
+    /// overrides on top; never saved, so none of it reaches other shells
+    pub fn session(&self) -> Result<Self, ConfigError> {
+        let (mut session, _) = Self::load(&self.config_path, layer::Scope::Session)?;
+        for entry in &mut session.path_entries {
+            if let Some(stored) = self.path_entries.iter().find(|e| e.path == entry.path) {
+                entry.discovered_paths = stored.discovered_paths.clone();
+                entry.rules_hash = stored.rules_hash;
+            }
         }
+        Ok(session)
+    }
 
-        if profile_path.exists() {
-            let content = fs::read_to_string(&profile_path)?;
-            if !content.contains(profile_tag) {
-                let mut file = fs::OpenOptions::new().append(true).open(&profile_path)?;
-                writeln!(
-                    file,
-                    "\n{profile_tag}\n[ -f {script_path} ] && . {script_path}\n",
-                    profile_tag = profile_tag,
-                    script_path = script_path.display()
-                )?;
+    /// Parse `content` as the config stored at `config_path`, locating any error
+    pub fn parse(config_path: &Path, content: &str) -> Result<Self, ConfigError> {
+        Self::parse_versioned(config_path, content).map(|(config, _)| config)
+    }
+
+    /// Like `parse`, also returning the changes needed to bring an older file up to date
+    pub fn parse_versioned(
+        config_path: &Path,
+        content: &str,
+    ) -> Result<(Self, Vec<String>), ConfigError> {
+        Self::parse_layer(config_path, content).map(|(config, _, changes)| (config, changes))
+    }
+
+    /// Like `parse_versioned`, also returning the upgraded table for merging with other layers
+    pub(super) fn parse_layer(
+        config_path: &Path,
+        content: &str,
+    ) -> Result<(Self, toml::Table, Vec<String>), ConfigError> {
+        let parse_error = |err: toml::de::Error| {
+            //@ Spans are byte offsets; turn the start into a 1-based line and column
+            let start = err.span().map_or(0, |span| span.start).min(content.len());
+            let before = &content[..start];
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+            ConfigError::Parse {
+                path: config_path.to_path_buf(),
+                line: before.matches('\n').count() + 1,
+                column: before[line_start..].chars().count() + 1,
+                message: err.message().to_string(),
             }
+        };
+
+        let mut table = toml::from_str::<toml::Table>(content).map_err(parse_error)?;
+        let changes = migrate::upgrade(&mut table).map_err(|version| ConfigError::Unsupported {
+            path: config_path.to_path_buf(),
+            version,
+        })?;
+
+        //@ Current files are read from the text, so field errors keep their location
+        let mut config = if changes.is_empty() {
+            toml::from_str::<Config>(content).map_err(parse_error)?
+        } else {
+            Config::deserialize(table.clone()).map_err(parse_error)?
+        };
+        config.config_path = config_path.to_path_buf();
+        config.check_names(|_| config_path.display().to_string())?;
+        Ok((config, table, changes))
+    }
+
+    /// Names go into scripts verbatim, so refuse any a shell would not read as a plain name
+    pub(super) fn check_names(&self, origin: impl Fn(&str) -> String) -> Result<(), ConfigError> {
+        match self.variables.iter().find(|v| !is_valid_name(&v.name)) {
+            Some(variable) => Err(ConfigError::VariableName {
+                origin: origin(&variable.name),
+                name: variable.name.clone(),
+            }),
+            None => Ok(()),
         }
+    }
 
-        // Include possible shell profiles
-        let shell_paths = vec![
-            home_dir.join(".bashrc"),
-            home_dir.join(".bash_profile"),
-            home_dir.join(".bash_login"),
-            home_dir.join(".zshrc"),
-            home_dir.join(".zprofile"),
-            home_dir.join(".config/fish/config.fish"),
-        ];
-
-        let source_lines = [
-            //? For Bash and ZSH
-            "\n[ -f \"$HOME/.profile\" ] && . \"$HOME/.profile\"\n",
-            //? For Fish
-            "\nif test -f ~/.profile; source ~/.profile; end\n",
-        ];
-
-        let index = 0;
-        for shell_path in shell_paths {
-            if !shell_path.exists() {
-                //@ Create the shell config file if it doesn't exist
-                let mut file = fs::File::create(shell_path.clone())?;
-                if shell_path.ends_with("config.fish") {
-                    //@ Use Fish syntax
-                    writeln!(file, "{}", source_lines[1])?;
-                } else {
-                    //@ Use Bash/ZSH syntax
-                    writeln!(file, "{}", source_lines[0])?;
-                }
-            } else {
-                let content = fs::read_to_string(&shell_path)?;
-                if shell_path.ends_with("config.fish") {
-                    if !content.contains("source ~/.profile") {
-                        let mut file = fs::OpenOptions::new().append(true).open(shell_path)?;
-                        writeln!(file, "{}", source_lines[1])?;
-                    }
-                } else if !content.contains(". ~/.profile")
-                    && !content.contains(". \"$HOME/.profile\"")
-                {
-                    let mut file = fs::OpenOptions::new().append(true).open(shell_path)?;
-                    writeln!(file, "{}", source_lines[0])?;
-                }
-            }
+    /// Every setting in effect, as it would be written
+    pub fn effective_table(&self) -> Result<toml::Table, Box<dyn Error>> {
+        match toml::Value::try_from(self)? {
+            toml::Value::Table(table) => Ok(table),
+            _ => Err("Config did not serialize to a table".into()),
         }
+    }
 
-        //@ Generate shell script
-        let mut script_contents = String::new();
-        script_contents.push_str("#!/bin/sh\n\n");
+    /// Content of the config file: settings from other layers stay where they came from
+    pub fn user_content(&self) -> Result<String, Box<dyn Error>> {
+        let user = self.layers.user_table(self.effective_table()?);
+        Ok(toml::to_string_pretty(&user)?)
+    }
 
-        //@ Export current PATH first to preserve existing entries
-        script_contents.push_str("export PATH=\"$PATH");
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let content = self.user_content()?;
 
-        //@ Add valid directories to PATH
-        for entry in &self.path_entries {
-            if entry.path.is_dir() {
-                script_contents.push_str(&format!(":{}", entry.path.display()));
+        //@ Never overwrite a file that stopped parsing after it was loaded, e.g. mid-edit
+        let current = fs::read_to_string(&self.config_path).ok();
+        if let Some(current) = &current {
+            if Self::parse(&self.config_path, current).is_err() {
+                return Err(ConfigError::Invalid {
+                    path: self.config_path.clone(),
+                }
+                .into());
             }
+        }
 
-            //@ Add only valid discovered directories
-            for (discovered, status) in &entry.discovered_paths {
-                if status.valid && discovered.is_dir() {
-                    script_contents.push_str(&format!(":{}", discovered.display()));
-                }
+        //@ Keep the previous content as a backup, but only when it actually changes
+        if current.as_deref() != Some(content.as_str()) {
+            if let Some(current) = current.filter(|current| !current.trim().is_empty()) {
+                backup::create(&self.config_path, &current)?;
             }
+            write_atomic(&self.config_path, &content)?;
         }
+        self.write_state()?;
+        self.write_scripts()?;
+        self.write_cache()?;
+        Ok(())
+    }
 
-        //@ Print a trailing newline
-        script_contents.push_str("\"\n");
+    /// Take each entry's scan results from the state file, where it has any
+    pub fn load_state(&mut self) {
+        let Some(mut state) =
+            state::State::read(&state::path(&self.config_path), &self.config_path)
+        else {
+            return;
+        };
+        for entry in &mut self.path_entries {
+            if let Some(discovered) = state.discovered.swap_remove(&entry.path) {
+                entry.discovered_paths = discovered;
+                entry.rules_hash = state
+                    .rules
+                    .get(&entry.path)
+                    .and_then(|hash| u64::from_str_radix(hash, 16).ok())
+                    .unwrap_or_default();
+            }
+        }
+    }
 
-        //@ Write the shell script
-        fs::write(script_path, script_contents)?;
+    /// Store scan results apart from the config, so the config holds only what the user set
+    pub fn write_state(&self) -> Result<(), Box<dyn Error>> {
+        let scanned = || {
+            self.path_entries
+                .iter()
+                .filter(|entry| !entry.discovered_paths.is_empty())
+        };
+        let state = state::State {
+            config_path: self.config_path.clone(),
+            discovered: scanned()
+                .map(|entry| (entry.path.clone(), entry.discovered_paths.clone()))
+                .collect(),
+            rules: scanned()
+                .map(|entry| (entry.path.clone(), format!("{:016x}", entry.rules_hash)))
+                .collect(),
+        };
+        state.write(&state::path(&self.config_path))
+    }
+
+    /// Write the resolved environment cache, keyed on the stored layers' current content
+    pub fn write_cache(&self) -> io::Result<()> {
+        let config_hash = layer::checksum(&self.config_path)?;
+        Cache::build(self, config_hash).write(&cache::path(&self.config_path))?;
 
+        //@ Older versions kept the caches next to the config, where they would get committed
+        for extension in ["cache", "index"] {
+            let _ = fs::remove_file(self.config_path.with_extension(extension));
+        }
         Ok(())
     }
 
-    pub fn should_exclude(&self, path: &Path, entry_excludes: &[String]) -> bool {
-        let path_str = path.to_string_lossy().to_lowercase();
-        let normalized_path = path_str.replace('/', MAIN_SEPARATOR_STR);
+    /// Regenerate the script for every shell; rc files are left to `hook install`
+    pub fn write_scripts(&self) -> Result<(), Box<dyn Error>> {
+        for shell in Shell::ALL {
+            write_atomic(&self.script_path(shell), shell.render(self)?)?;
+        }
+        Ok(())
+    }
+
+    /// Location of the script generated for `shell`, next to the config file
+    pub fn script_path(&self, shell: Shell) -> PathBuf {
+        self.config_path.with_extension(shell.extension())
+    }
+
+    /// Rescan recursive entries, concurrently; `full` ignores what is already known
+    pub fn refresh_discovered(&mut self, full: bool, jobs: usize) -> Result<(), Box<dyn Error>> {
+        //@ Build every entry's rules first so a bad pattern fails before any scanning
+        let mut scans = Vec::new();
+        for (index, entry) in self.path_entries.iter().enumerate() {
+            if entry.max_depth > 1 {
+                let rules = exclude::Rules::new(
+                    &entry.path,
+                    &entry.exclude_patterns,
+                    &self.default_excludes,
+                    entry.bins_only,
+                )?;
+                let hash = discover::rules_hash(entry, &self.default_excludes);
+                scans.push((index, rules, hash));
+            }
+        }
+
+        //@ Scan entries concurrently, collecting results back in entry order
+        let entries = &self.path_entries;
+        let results = thread::scope(|scope| {
+            let handles = scans
+                .iter()
+                .map(|(index, rules, hash)| {
+                    let entry = &entries[*index];
+                    scope.spawn(move || {
+                        //@ Results from other rules cannot be reused, however old the mtimes
+                        if full || entry.discovered_paths.is_empty() || entry.rules_hash != *hash {
+                            discover::scan(entry, rules, jobs)
+                        } else {
+                            discover::rescan(entry, rules)
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join())
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|_| "Directory scan panicked")?;
+
+        for ((index, _, hash), discovered) in scans.iter().zip(results) {
+            let entry = &mut self.path_entries[*index];
+            entry.discovered_paths = discovered;
+            entry.rules_hash = *hash;
+            entry.order.arrange(&mut entry.discovered_paths);
+        }
+        Ok(())
+    }
 
-        //@ Split path into components using OS-specific separator
-        let components: Vec<&str> = normalized_path
-            .split(MAIN_SEPARATOR)
-            .filter(|s| !s.is_empty())
-            .collect();
+    /// Entries by descending priority, keeping their stored order on ties
+    pub fn ordered_entries(&self) -> Vec<&entry::Path> {
+        let mut entries: Vec<_> = self.path_entries.iter().collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.priority));
+        entries
+    }
 
-        //@ Check each component against exclusion patterns
-        for exclude in self.default_excludes.iter().chain(entry_excludes.iter()) {
-            let exclude = exclude.to_lowercase();
+    /// Directories contributed to PATH, split into those placed ahead of the
+    /// inherited PATH and those placed after it
+    pub fn path_directories(&self) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let mut prepend: Vec<PathBuf> = Vec::new();
+        let mut append: Vec<PathBuf> = Vec::new();
+        let mut seen = HashSet::new();
 
-            //@ Check full path
-            if normalized_path.contains(&format!(
-                "{}{}{}",
-                MAIN_SEPARATOR_STR, exclude, MAIN_SEPARATOR_STR
-            )) || normalized_path.ends_with(&format!("{}{}", MAIN_SEPARATOR_STR, exclude))
-            {
-                return true;
+        for entry in self.ordered_entries() {
+            let mut dirs = Vec::new();
+            if entry.path.is_dir() && entry.min_depth == 0 && entry.root_qualifies() {
+                dirs.push(entry.path.clone());
             }
 
-            //@ Check individual components for exact matches
-            if components.iter().any(|&c| c == exclude) {
-                return true;
+            //@ Add only valid discovered directories, in their stored order
+            dirs.extend(
+                entry
+                    .discovered_paths
+                    .iter()
+                    .filter(|(path, status)| status.valid && path.is_dir())
+                    .map(|(path, _)| path.clone()),
+            );
+
+            dirs.retain(|dir| seen.insert(dir.clone()));
+            if entry.prepend {
+                prepend.extend(dirs);
+            } else {
+                append.extend(dirs);
             }
         }
 
-        //@ Check gitignore rules (unchanged)
-        if let Some(parent) = path.parent() {
-            let walker = WalkBuilder::new(parent)
-                .hidden(true)
-                .git_ignore(true)
-                .ignore(true)
-                .build();
-
-            for entry in walker.flatten() {
-                if entry.path() == path {
-                    return entry.path().starts_with(".git") || entry.depth() == 0;
-                }
-            }
+        (prepend, append)
+    }
+
+    /// PATH as it will be after the generated script runs on top of `inherited`
+    pub fn effective_path(&self, inherited: &[PathBuf]) -> Vec<PathBuf> {
+        let (prepend, append) = self.path_directories();
+
+        //@ Prepended entries move ahead of any inherited copy
+        let mut path = prepend.clone();
+        path.extend(
+            inherited
+                .iter()
+                .filter(|dir| !prepend.contains(dir))
+                .cloned(),
+        );
+        path.extend(append.into_iter().filter(|dir| !inherited.contains(dir)));
+        path
+    }
+
+    pub fn set_variable(&mut self, variable: entry::Variable) {
+        match self.variables.iter_mut().find(|v| v.name == variable.name) {
+            Some(existing) => *existing = variable,
+            None => self.variables.push(variable),
         }
+    }
 
-        false
+    pub fn get_variable(&self, name: &str) -> Option<&entry::Variable> {
+        self.variables.iter().find(|v| v.name == name)
     }
 }
@@ -0,0 +1,234 @@
+use super::{cache, entry, exclude};
+use clap::ValueEnum;
+use ignore::{WalkBuilder, WalkState};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Order {
+    #[default]
+    DepthFirst, // Walk order, each directory followed by its children
+    BreadthFirst, // Shallow directories before deeper ones
+    Alphabetical, // Sorted by full path
+}
+
+impl Order {
+    /// Arrange directories discovered in depth-first walk order
+    pub fn arrange(self, discovered: &mut IndexMap<PathBuf, exclude::Check>) {
+        match self {
+            Self::DepthFirst => {}
+            Self::BreadthFirst => {
+                //@ Stable, so siblings keep their walk order
+                discovered.sort_by_cached_key(|path, _| path.components().count());
+            }
+            Self::Alphabetical => discovered.sort_keys(),
+        }
+    }
+}
+
+/// Hash of everything deciding what a scan of `entry` finds, so stale results can be told apart
+pub fn rules_hash(entry: &entry::Path, default_excludes: &[String]) -> u64 {
+    let rules = format!(
+        "{:?}|{:?}|{}|{}|{}|{}",
+        entry.exclude_patterns,
+        default_excludes,
+        entry.bins_only,
+        entry.min_depth,
+        entry.max_depth,
+        entry.follow_symlinks
+    );
+    cache::checksum(rules.as_bytes())
+}
+
+/// Scan an entry with a parallel walker; `jobs` of 0 picks a thread count automatically
+pub fn scan(
+    entry: &entry::Path,
+    rules: &exclude::Rules,
+    jobs: usize,
+) -> IndexMap<PathBuf, exclude::Check> {
+    let root = entry.path.as_path();
+    let mut discovered = IndexMap::new();
+
+    if !root.exists() {
+        discovered.insert(
+            root.to_path_buf(),
+            exclude::Check::new_invalid(exclude::Reason::DoesNotExist),
+        );
+        return discovered;
+    }
+
+    if !root.is_dir() {
+        discovered.insert(
+            root.to_path_buf(),
+            exclude::Check::new_invalid(exclude::Reason::NotDirectory),
+        );
+        return discovered;
+    }
+
+    let found = Mutex::new(Vec::new());
+    walker(entry, rules, root, entry.max_depth as usize)
+        .threads(jobs)
+        .build_parallel()
+        .run(|| {
+            let found = &found;
+            Box::new(move |result| {
+                let item = match result {
+                    Ok(dir_entry) => {
+                        let path = dir_entry.path();
+                        if !path.is_dir() || dir_entry.depth() < entry.min_depth as usize {
+                            return WalkState::Continue;
+                        }
+                        (path.to_path_buf(), rules.check(path))
+                    }
+                    Err(err) => (
+                        error_path(&err).unwrap_or(root).to_path_buf(),
+                        exclude::Check::new_invalid(error_reason(&err)),
+                    ),
+                };
+                found.lock().unwrap().push(item);
+                WalkState::Continue
+            })
+        });
+
+    //@ Sorting by path gives the same depth-first, name-ordered result as a serial walk
+    let mut found = found.into_inner().unwrap();
+    found.sort_by(|(a, _), (b, _)| a.cmp(b));
+    discovered.extend(found);
+    discovered
+}
+
+/// Rescan an entry, reading only directories modified since their last check
+pub fn rescan(entry: &entry::Path, rules: &exclude::Rules) -> IndexMap<PathBuf, exclude::Check> {
+    let root = entry.path.as_path();
+    if !root.is_dir() {
+        return scan(entry, rules, 1);
+    }
+
+    let previous = &entry.discovered_paths;
+    let mut known_children: HashMap<&Path, Vec<&PathBuf>> = HashMap::new();
+    for path in previous.keys() {
+        if let Some(parent) = path.parent() {
+            known_children.entry(parent).or_default().push(path);
+        }
+    }
+    let mut discovered = IndexMap::new();
+
+    //@ Depth-first, in file name order, to match a full walk
+    let mut pending = vec![(root.to_path_buf(), 0usize, true)];
+    while let Some((dir, depth, descend)) = pending.pop() {
+        //@ A directory that is gone drops out along with its subtree
+        let Ok(modified) = fs::metadata(&dir).and_then(|metadata| metadata.modified()) else {
+            continue;
+        };
+
+        //@ A directory's mtime only moves when its own listing changes
+        let unchanged = previous
+            .get(&dir)
+            .filter(|check| modified <= check.timestamp);
+
+        if depth >= entry.min_depth as usize {
+            //@ Making a file executable leaves the mtime alone, so bins-only checks are redone
+            let check = match unchanged {
+                Some(check) if !entry.bins_only => check.clone(),
+                _ => rules.check(&dir),
+            };
+            discovered.insert(dir.clone(), check);
+        }
+
+        if !descend || depth >= entry.max_depth as usize {
+            continue;
+        }
+
+        let mut children = if unchanged.is_some() {
+            //@ Reuse the known children instead of reading the directory again
+            known_children
+                .get(dir.as_path())
+                .map(|children| children.iter().map(|path| path.to_path_buf()).collect())
+                .unwrap_or_default()
+        } else {
+            let mut children = Vec::new();
+            for result in walker(entry, rules, &dir, 1).build() {
+                match result {
+                    Ok(dir_entry) if dir_entry.depth() == 1 && dir_entry.path().is_dir() => {
+                        children.push(dir_entry.into_path());
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        discovered.insert(
+                            error_path(&err).unwrap_or(&dir).to_path_buf(),
+                            exclude::Check::new_invalid(error_reason(&err)),
+                        );
+                    }
+                }
+            }
+            children
+        };
+        children.sort();
+
+        for child in children.into_iter().rev() {
+            //@ Only descend into links when the entry follows them
+            let is_link = fs::symlink_metadata(&child)
+                .map(|metadata| metadata.file_type().is_symlink())
+                .unwrap_or(false);
+            pending.push((child, depth + 1, entry.follow_symlinks || !is_link));
+        }
+    }
+    discovered
+}
+
+/// Walk `root` with the entry's ignore and link settings, skipping what excluded
+/// directories hold
+fn walker(
+    entry: &entry::Path,
+    rules: &exclude::Rules,
+    root: &Path,
+    max_depth: usize,
+) -> WalkBuilder {
+    let rules = rules.clone();
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .hidden(false)
+        .git_ignore(true)
+        .ignore(true)
+        .max_depth(Some(max_depth))
+        .follow_links(entry.follow_symlinks)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        //@ An excluded directory is still reported, but not entered
+        .filter_entry(move |dir_entry| {
+            dir_entry.depth() == 0
+                || dir_entry
+                    .path()
+                    .parent()
+                    .is_none_or(|parent| !rules.prunes(parent))
+        });
+    builder
+}
+
+/// The path a walk error is about, when it names one
+fn error_path(err: &ignore::Error) -> Option<&Path> {
+    match err {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::Loop { child, .. } => Some(child),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            error_path(err)
+        }
+        ignore::Error::Partial(errs) => errs.iter().find_map(error_path),
+        _ => None,
+    }
+}
+
+fn error_reason(err: &ignore::Error) -> exclude::Reason {
+    match err.io_error() {
+        Some(io_err) => match io_err.kind() {
+            std::io::ErrorKind::PermissionDenied => exclude::Reason::PermissionDenied,
+            _ => exclude::Reason::Other(err.to_string()),
+        },
+        None => exclude::Reason::Other(err.to_string()),
+    }
+}
@@ -1,13 +1,52 @@
-use super::exclude;
+use super::{discover, exclude, variable};
+use crate::utilities::contains_executable;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::PathBuf};
+use std::path::PathBuf;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Path {
     pub path: PathBuf,
     pub prepend: bool,
+    #[serde(default)]
+    pub priority: i32,
     pub exclude_patterns: Vec<String>,
     pub max_depth: u8,
     #[serde(default)]
-    pub discovered_paths: HashMap<PathBuf, exclude::Check>,
+    pub min_depth: u8,
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    #[serde(default)]
+    pub order: discover::Order,
+    #[serde(default)]
+    pub bins_only: bool,
+    /// Scan results, stored in the state file; older configs may still carry them
+    #[serde(default, skip_serializing)]
+    pub discovered_paths: IndexMap<PathBuf, exclude::Check>,
+    /// Hash of the rules `discovered_paths` was scanned with, also kept in the state file
+    #[serde(skip)]
+    pub rules_hash: u64,
+}
+
+impl Path {
+    /// Whether the root itself belongs on PATH: with `bins_only`, only when it holds executables
+    pub fn root_qualifies(&self) -> bool {
+        !self.bins_only || contains_executable(&self.path)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Variable {
+    pub name: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original: Option<String>,
+    #[serde(default)]
+    pub kind: variable::Kind,
+    #[serde(default)]
+    pub scope: variable::Scope,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lookup_order: Vec<PathBuf>,
 }
@@ -1,5 +1,6 @@
+use crate::utilities::{contains_executable, PatternSet};
 use serde::{Deserialize, Serialize};
-use std::time::SystemTime;
+use std::{error::Error, path::Path, time::SystemTime};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum Reason {
@@ -9,10 +10,11 @@ pub enum Reason {
     NotDirectory,           // Path exists but isn't a directory
     DoesNotExist,           // Path doesn't exist
     PermissionDenied,       // No permission to access
+    NoExecutables,          // Holds no executable files
     Other(String),          // Other reasons
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Check {
     pub timestamp: SystemTime,
     pub valid: bool,
@@ -53,3 +55,108 @@ impl Check {
         }
     }
 }
+
+/// The exclusion rules of one PATH entry, shared by scanning and `path why`
+#[derive(Clone)]
+pub struct Rules {
+    entry: PatternSet,
+    defaults: PatternSet,
+    bins_only: bool,
+}
+
+/// Outcome of the rules for a single directory, with the rule that decided it
+pub struct Decision {
+    pub excluded: Option<Reason>,
+    pub rule: String,
+}
+
+impl Rules {
+    pub fn new(
+        root: &Path,
+        exclude_patterns: &[String],
+        default_excludes: &[String],
+        bins_only: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            entry: PatternSet::new(root, exclude_patterns)?,
+            defaults: PatternSet::new(root, default_excludes)?,
+            bins_only,
+        })
+    }
+
+    pub fn decide(&self, path: &Path) -> Decision {
+        //@ Patterns on the entry take precedence over the defaults; a re-include only cancels
+        //@ pattern exclusions, so the checks below still apply
+        let re_included = match self.entry.check(path, true) {
+            Some(found) if found.negated => {
+                Some(format!("re-included by entry pattern '{}'", found.pattern))
+            }
+            Some(found) => {
+                return Decision::excluded(
+                    Reason::ExcludePattern(found.pattern.clone()),
+                    format!("excluded by entry pattern '{}'", found.pattern),
+                );
+            }
+            None => match self.defaults.check(path, true) {
+                Some(found) if found.negated => Some(format!(
+                    "re-included by default pattern '{}'",
+                    found.pattern
+                )),
+                Some(found) => {
+                    return Decision::excluded(
+                        Reason::DefaultExclude(found.pattern.clone()),
+                        format!("excluded by default pattern '{}'", found.pattern),
+                    );
+                }
+                None => None,
+            },
+        };
+
+        if path.components().any(|comp| comp.as_os_str() == ".git") {
+            return Decision::excluded(Reason::GitIgnored, "inside a .git directory".to_string());
+        }
+
+        if self.bins_only && !contains_executable(path) {
+            return Decision::excluded(
+                Reason::NoExecutables,
+                "holds no executables (bins-only)".to_string(),
+            );
+        }
+
+        Decision::included(re_included.unwrap_or_else(|| "no exclude pattern matched".to_string()))
+    }
+
+    /// Whether everything below `dir` is excluded, so a walk need not go into it
+    pub fn prunes(&self, dir: &Path) -> bool {
+        //@ Without re-includes, patterns matching a directory match all it holds as well
+        if self.entry.has_negations() || self.defaults.has_negations() {
+            return false;
+        }
+        self.entry.check(dir, true).is_some()
+            || self.defaults.check(dir, true).is_some()
+            || dir.components().any(|comp| comp.as_os_str() == ".git")
+    }
+
+    pub fn check(&self, path: &Path) -> Check {
+        match self.decide(path).excluded {
+            Some(reason) => Check::new_invalid(reason),
+            None => Check::new_valid(),
+        }
+    }
+}
+
+impl Decision {
+    fn included(rule: String) -> Self {
+        Self {
+            excluded: None,
+            rule,
+        }
+    }
+
+    fn excluded(reason: Reason, rule: String) -> Self {
+        Self {
+            excluded: Some(reason),
+            rule,
+        }
+    }
+}
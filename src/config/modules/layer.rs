@@ -0,0 +1,358 @@
+use super::{
+    cache,
+    config::{Config, ConfigError},
+    migrate, trust,
+};
+use indexmap::IndexMap;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+};
+use toml::{Table, Value};
+
+/// System-wide settings, below the user's own config
+pub const SYSTEM_CONFIG: &str = "/etc/dotsrus/config.toml";
+
+/// Per-directory settings, found in the working directory or the nearest parent having one,
+/// and applied only once allowed
+pub const PROJECT_CONFIG: &str = ".dotsrus.toml";
+
+/// Overrides `default_excludes` with a comma-separated list
+const ENV_EXCLUDES: &str = "DOTSRUS_DEFAULT_EXCLUDES";
+
+/// Prefix of overrides setting a variable's value, e.g. `DOTSRUS_VAR_EDITOR=nvim`
+const ENV_VARIABLE: &str = "DOTSRUS_VAR_";
+
+/// Which layers to merge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Stored,  // System and user configs, what the saved scripts and caches are built from
+    Session, // Also an allowed project config and environment overrides, for one shell
+}
+
+/// Where an effective setting came from, lowest precedence first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    Default,          // Built into dotsrus, no layer sets it
+    System(PathBuf),  // The system-wide config
+    User(PathBuf),    // The config dotsrus saves to
+    Project(PathBuf), // A per-directory config
+    Env(String),      // An environment override
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "built-in default"),
+            Self::System(path) | Self::User(path) | Self::Project(path) => {
+                write!(f, "{}", path.display())
+            }
+            Self::Env(name) => write!(f, "${}", name),
+        }
+    }
+}
+
+/// Where each effective setting came from, and what saving needs to keep the layers apart
+#[derive(Debug, Default)]
+pub struct Layers {
+    origins: HashMap<String, Origin>,
+    inherited: HashMap<String, Value>, // Settings from other layers, as loaded
+    shadowed: HashMap<String, Value>,  // User settings hidden by a higher layer
+}
+
+impl Layers {
+    /// Origin of the setting under `key`, as named by `key`, `entry_key` or `variable_key`
+    pub fn origin(&self, key: &str) -> &Origin {
+        self.origins.get(key).unwrap_or(&Origin::Default)
+    }
+
+    /// Origin of the setting under `key` when a layer other than the user config sets it
+    pub fn inherited_from(&self, key: &str) -> Option<&Origin> {
+        self.origins
+            .get(key)
+            .filter(|origin| !matches!(origin, Origin::Default | Origin::User(_)))
+    }
+
+    /// Reduce the effective settings to what belongs in the user config: its own settings,
+    /// and those from other layers only where they were changed after loading
+    pub fn user_table(&self, effective: Table) -> Table {
+        let mut user = Table::new();
+        for (field, value) in effective {
+            match (field.as_str(), value) {
+                (section @ ("path_entries" | "variables"), Value::Array(items)) => {
+                    let items = items
+                        .into_iter()
+                        .filter_map(|item| {
+                            let key = item_key(section, &item)?;
+                            self.keep(&key, item)
+                        })
+                        .collect();
+                    user.insert(field, Value::Array(items));
+                }
+                (_, value) => {
+                    if let Some(value) = self.keep(&field, value) {
+                        user.insert(field, value);
+                    }
+                }
+            }
+        }
+        user
+    }
+
+    fn keep(&self, key: &str, value: Value) -> Option<Value> {
+        match self.origins.get(key) {
+            None | Some(Origin::Default | Origin::User(_)) => Some(value),
+            Some(_) if self.inherited.get(key) != Some(&value) => Some(value),
+            Some(_) => self.shadowed.get(key).cloned(),
+        }
+    }
+}
+
+/// Key of the path entry for `path`
+pub fn entry_key(path: &str) -> String {
+    format!("path_entries.{}", Value::from(path))
+}
+
+/// Key of the variable `name`
+pub fn variable_key(name: &str) -> String {
+    format!("variables.{}", name)
+}
+
+fn item_key(section: &str, item: &Value) -> Option<String> {
+    match section {
+        "path_entries" => item.get("path")?.as_str().map(entry_key),
+        _ => item.get("name")?.as_str().map(variable_key),
+    }
+}
+
+/// Merge the system config and the user's `user` table read from `config_path`, then for a
+/// session the allowed project config and environment overrides, each overriding the ones
+/// before it
+pub fn load(config_path: &Path, user: Table, scope: Scope) -> Result<Config, ConfigError> {
+    let mut merged = Merged::default();
+    let system = Path::new(SYSTEM_CONFIG);
+    if let Some(table) = read(system)? {
+        merged.apply(Origin::System(system.to_path_buf()), table);
+    }
+    merged.apply(Origin::User(config_path.to_path_buf()), user);
+    if scope == Scope::Session {
+        if let Some((project, content)) = allowed_project(config_path) {
+            let (_, mut table, _) = Config::parse_layer(&project, &content)?;
+            resolve_entries(&mut table, project.parent().unwrap_or(Path::new("/")));
+            merged.apply(Origin::Project(project), table);
+        }
+        merged.apply_env(env_overrides());
+    }
+
+    let mut config = Config::deserialize(merged.table()).map_err(|err| ConfigError::Layers {
+        message: err.message().to_string(),
+    })?;
+    config.config_path = config_path.to_path_buf();
+
+    //@ Files were checked as they were read, but environment overrides name variables too
+    config.check_names(|name| {
+        merged.settings.get(&variable_key(name)).map_or_else(
+            || config_path.display().to_string(),
+            |(origin, _)| origin.to_string(),
+        )
+    })?;
+
+    //@ Compare against settings as they serialize, so defaulted fields do not count as edits
+    let mut layers = Layers::default();
+    let effective = config
+        .effective_table()
+        .map_err(|err| ConfigError::Layers {
+            message: err.to_string(),
+        })?;
+    for (key, value) in flatten(effective) {
+        match merged.settings.get(&key) {
+            Some((Origin::User(_), _)) | None => {}
+            Some(_) => {
+                layers.inherited.insert(key.clone(), value);
+                if let Some(user_value) = merged.user.remove(&key) {
+                    layers.shadowed.insert(key, user_value);
+                }
+            }
+        }
+    }
+    layers.origins = merged
+        .settings
+        .into_iter()
+        .map(|(key, (origin, _))| (key, origin))
+        .collect();
+    config.layers = layers;
+    Ok(config)
+}
+
+/// Hash of the stored layers' content, so anything built from them can tell when it goes stale
+pub fn checksum(config_path: &Path) -> io::Result<u64> {
+    let mut bytes = fs::read(config_path)?;
+    if let Ok(content) = fs::read(SYSTEM_CONFIG) {
+        bytes.push(0);
+        bytes.extend(content);
+    }
+    Ok(cache::checksum(&bytes))
+}
+
+/// Whether a session differs from the stored layers: an allowed project config or
+/// environment overrides apply
+pub fn session_differs(config_path: &Path) -> bool {
+    !env_overrides().is_empty() || allowed_project(config_path).is_some()
+}
+
+/// The nearest project config at or above the working directory, unless it is the user's own
+pub fn project_config(config_path: &Path) -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG))
+        .find(|path| path.is_file())
+        .filter(|path| path != config_path)
+}
+
+/// The nearest project config when it is left out, not being allowed as it reads now
+pub fn disallowed_project(config_path: &Path) -> Option<PathBuf> {
+    let project = project_config(config_path)?;
+    allowed_project(config_path).is_none().then_some(project)
+}
+
+/// The nearest project config with its content, if it was allowed exactly as it reads now
+fn allowed_project(config_path: &Path) -> Option<(PathBuf, String)> {
+    let project = project_config(config_path)?;
+    let content = fs::read_to_string(&project).ok()?;
+    trust::Trusted::read(config_path)
+        .allows(&project, &content)
+        .then_some((project, content))
+}
+
+/// Read, upgrade and validate a layer, `None` when it does not exist
+fn read(path: &Path) -> Result<Option<Table>, ConfigError> {
+    match fs::read_to_string(path) {
+        Ok(content) => Config::parse_layer(path, &content).map(|(_, table, _)| Some(table)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(source) => Err(ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        }),
+    }
+}
+
+/// Make relative entry paths in a project config relative to its directory
+fn resolve_entries(table: &mut Table, dir: &Path) {
+    let Some(Value::Array(entries)) = table.get_mut("path_entries") else {
+        return;
+    };
+    for entry in entries {
+        if let Some(Value::String(path)) = entry.get_mut("path") {
+            if Path::new(path).is_relative() {
+                *path = dir.join(&*path).display().to_string();
+            }
+        }
+    }
+}
+
+/// Recognised `DOTSRUS_*` variables, sorted by name
+fn env_overrides() -> Vec<(String, String)> {
+    let mut overrides: Vec<_> = env::vars()
+        .filter(|(name, _)| name == ENV_EXCLUDES || name.starts_with(ENV_VARIABLE))
+        .collect();
+    overrides.sort();
+    overrides
+}
+
+/// Settings split into top-level fields and keyed entries and variables, in the order first set
+#[derive(Default)]
+struct Merged {
+    settings: IndexMap<String, (Origin, Value)>,
+    user: HashMap<String, Value>, // What the user config itself sets
+}
+
+impl Merged {
+    fn apply(&mut self, origin: Origin, table: Table) {
+        for (field, value) in table {
+            match (field.as_str(), value) {
+                ("version", _) => {}
+                (section @ ("path_entries" | "variables"), Value::Array(items)) => {
+                    for item in items {
+                        if let Some(key) = item_key(section, &item) {
+                            self.set(key, origin.clone(), item);
+                        }
+                    }
+                }
+                (_, value) => self.set(field, origin.clone(), value),
+            }
+        }
+    }
+
+    fn apply_env(&mut self, overrides: Vec<(String, String)>) {
+        for (name, value) in overrides {
+            let origin = Origin::Env(name.clone());
+            if name == ENV_EXCLUDES {
+                let patterns = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|pattern| !pattern.is_empty())
+                    .map(Value::from)
+                    .collect();
+                self.set("default_excludes".into(), origin, Value::Array(patterns));
+            } else if let Some(variable) = name.strip_prefix(ENV_VARIABLE) {
+                //@ Only the value changes; an existing variable keeps its kind and scope
+                let key = variable_key(variable);
+                let mut item = match self.settings.get(&key) {
+                    Some((_, Value::Table(item))) => item.clone(),
+                    _ => Table::from_iter([("name".to_string(), Value::from(variable))]),
+                };
+                item.insert("value".into(), Value::from(value));
+                self.set(key, origin, Value::Table(item));
+            }
+        }
+    }
+
+    fn set(&mut self, key: String, origin: Origin, value: Value) {
+        if matches!(origin, Origin::User(_)) {
+            self.user.insert(key.clone(), value.clone());
+        }
+        self.settings.insert(key, (origin, value));
+    }
+
+    fn table(&self) -> Table {
+        let mut table = Table::new();
+        table.insert("version".into(), Value::from(migrate::CURRENT_VERSION));
+        for (key, (_, value)) in &self.settings {
+            let section = ["path_entries", "variables"]
+                .into_iter()
+                .find(|section| key.starts_with(&format!("{}.", section)));
+            match section {
+                Some(section) => table
+                    .entry(section)
+                    .or_insert_with(|| Value::Array(Vec::new()))
+                    .as_array_mut()
+                    .expect("sections hold arrays")
+                    .push(value.clone()),
+                None => {
+                    table.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        table
+    }
+}
+
+/// Effective settings keyed as in `Layers`
+pub fn flatten(table: Table) -> Vec<(String, Value)> {
+    let mut settings = Vec::new();
+    for (field, value) in table {
+        match (field.as_str(), value) {
+            (section @ ("path_entries" | "variables"), Value::Array(items)) => {
+                settings.extend(
+                    items
+                        .into_iter()
+                        .filter_map(|item| Some((item_key(section, &item)?, item))),
+                );
+            }
+            (_, value) => settings.push((field, value)),
+        }
+    }
+    settings
+}
@@ -0,0 +1,148 @@
+use super::{
+    cache::{self, Sources},
+    config::Config,
+    layer, state,
+};
+use crate::utilities::is_executable;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const MAGIC: &[u8; 4] = b"DRUI";
+const VERSION: u16 = 1;
+
+/// Executable names mapped to every managed directory providing them, in PATH order
+#[derive(Debug)]
+pub struct Index {
+    pub config_hash: u64,
+    pub sources: Sources,
+    pub commands: BTreeMap<String, Vec<usize>>,
+}
+
+impl Index {
+    /// Read the index kept for `config`, rebuilding and storing it when stale
+    pub fn load(config: &Config) -> io::Result<Self> {
+        let config_hash = layer::checksum(&config.config_path)?;
+        let index_path = path(&config.config_path);
+
+        if let Some(index) = fs::read(&index_path)
+            .ok()
+            .and_then(|bytes| Self::decode(&bytes))
+        {
+            if index.config_hash == config_hash && cache::unchanged(&index.sources) {
+                return Ok(index);
+            }
+        }
+
+        let index = Self::build(config, config_hash);
+        cache::write_or_remove(&index_path, index.encode())?;
+        Ok(index)
+    }
+
+    /// Read every managed directory, in the order the generated script puts them on PATH
+    pub fn build(config: &Config, config_hash: u64) -> Self {
+        let (prepend, append) = config.path_directories();
+        let dirs: Vec<_> = prepend.into_iter().chain(append).collect();
+
+        let mut commands: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (position, dir) in dirs.iter().enumerate() {
+            let Ok(read_dir) = fs::read_dir(dir) else {
+                continue;
+            };
+            for dir_entry in read_dir.flatten() {
+                let Ok(name) = dir_entry.file_name().into_string() else {
+                    continue;
+                };
+                if is_executable(&dir_entry.path()) {
+                    commands.entry(name).or_default().push(position);
+                }
+            }
+        }
+
+        Self {
+            config_hash,
+            sources: cache::stamp(dirs),
+            commands,
+        }
+    }
+
+    /// Managed directories providing `name`, the first one winning
+    pub fn lookup(&self, name: &str) -> Vec<&Path> {
+        self.commands
+            .get(name)
+            .map(|positions| positions.iter().map(|&i| self.dir(i)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Names provided by more than one managed directory
+    pub fn conflicts(&self) -> impl Iterator<Item = (&str, Vec<&Path>)> {
+        self.commands
+            .iter()
+            .filter(|(_, positions)| positions.len() > 1)
+            .map(|(name, positions)| {
+                let dirs = positions.iter().map(|&i| self.dir(i)).collect();
+                (name.as_str(), dirs)
+            })
+    }
+
+    /// Directories the index was built from
+    pub fn dirs(&self) -> impl Iterator<Item = &Path> {
+        self.sources.iter().map(|(dir, _)| dir.as_path())
+    }
+
+    fn dir(&self, position: usize) -> &Path {
+        &self.sources[position].0
+    }
+
+    /// `None` when a path is not UTF-8
+    pub fn encode(&self) -> Option<Vec<u8>> {
+        let mut payload = Vec::new();
+        payload.extend(self.config_hash.to_le_bytes());
+        cache::put_sources(&mut payload, &self.sources)?;
+
+        cache::put_len(&mut payload, self.commands.len());
+        for (name, positions) in &self.commands {
+            cache::put_str(&mut payload, name);
+            cache::put_len(&mut payload, positions.len());
+            for &position in positions {
+                cache::put_len(&mut payload, position);
+            }
+        }
+
+        Some(cache::seal(MAGIC, VERSION, payload))
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut reader = cache::open(MAGIC, VERSION, bytes)?;
+        let config_hash = reader.u64()?;
+        let sources = cache::read_sources(&mut reader)?;
+
+        let mut commands = BTreeMap::new();
+        for _ in 0..reader.u32()? {
+            let name = reader.str()?.to_string();
+            let mut positions = Vec::new();
+            for _ in 0..reader.u32()? {
+                let position = reader.u32()? as usize;
+                //@ Positions must point at a recorded directory
+                if position >= sources.len() {
+                    return None;
+                }
+                positions.push(position);
+            }
+            commands.insert(name, positions);
+        }
+
+        Some(Self {
+            config_hash,
+            sources,
+            commands,
+        })
+    }
+}
+
+/// Location of the command index kept for the config at `config_path`
+pub fn path(config_path: &Path) -> PathBuf {
+    state::dir(config_path).join("commands.index")
+}
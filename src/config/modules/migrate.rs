@@ -0,0 +1,141 @@
+use toml::{Table, Value};
+
+/// Schema version this build reads and writes
+pub const CURRENT_VERSION: u32 = 2;
+
+/// Version of files written before the field existed
+const UNVERSIONED: u32 = 1;
+
+/// Version assumed for files that set nothing at all
+pub fn current_version() -> u32 {
+    CURRENT_VERSION
+}
+
+/// Rewrites a table to the next version, returning what it changed
+type Migration = fn(&mut Table) -> Vec<String>;
+
+/// Each migration with the version it produces, in order
+const MIGRATIONS: &[(u32, Migration)] = &[(2, separate_scan_state)];
+
+/// Schema version of a parsed config file
+pub fn version(table: &Table) -> u32 {
+    table
+        .get("version")
+        .and_then(Value::as_integer)
+        .map_or(UNVERSIONED, |version| version as u32)
+}
+
+/// Bring `table` up to the current version, describing each change; empty when it already was.
+/// Fails with the file's version when it was written by a newer build
+pub fn upgrade(table: &mut Table) -> Result<Vec<String>, u32> {
+    //@ An empty file has nothing to upgrade
+    if table.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let from = version(table);
+    if from > CURRENT_VERSION {
+        return Err(from);
+    }
+
+    let mut changes = Vec::new();
+    for (to, migration) in MIGRATIONS.iter().filter(|(to, _)| *to > from) {
+        changes.extend(
+            migration(table)
+                .into_iter()
+                .map(|change| format!("version {}: {}", to, change)),
+        );
+    }
+
+    if from < CURRENT_VERSION {
+        table.insert("version".into(), Value::Integer(CURRENT_VERSION.into()));
+        changes.push(format!("set version from {} to {}", from, CURRENT_VERSION));
+    }
+    Ok(changes)
+}
+
+/// 1 to 2: machine-specific fields leave the shared config
+fn separate_scan_state(table: &mut Table) -> Vec<String> {
+    let mut changes = Vec::new();
+    if table.remove("config_path").is_some() {
+        changes.push("dropped `config_path`, which is where the file already is".to_string());
+    }
+
+    //@ Scan results stay in the table so loading can carry them over to the state file
+    let scanned = table
+        .get("path_entries")
+        .and_then(Value::as_array)
+        .map_or(0, |entries| {
+            entries
+                .iter()
+                .filter(|entry| entry.get("discovered_paths").is_some())
+                .count()
+        });
+    if scanned > 0 {
+        changes.push(format!(
+            "moved scan results of {} entries to the state file",
+            scanned
+        ));
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(content: &str) -> Table {
+        toml::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn version_1_files_lose_machine_specific_fields() {
+        let mut v1 = table(
+            r#"
+            config_path = "/home/me/.config/dotsrus/config.toml"
+
+            [[path_entries]]
+            path = "/opt/tools"
+            prepend = false
+            exclude_patterns = []
+            max_depth = 3
+            discovered_paths = {}
+            "#,
+        );
+        let changes = upgrade(&mut v1).unwrap();
+
+        assert!(!v1.contains_key("config_path"));
+        assert_eq!(version(&v1), CURRENT_VERSION);
+        assert_eq!(
+            changes,
+            [
+                "version 2: dropped `config_path`, which is where the file already is",
+                "version 2: moved scan results of 1 entries to the state file",
+                "set version from 1 to 2",
+            ]
+        );
+        //@ Loading moves the scan results to the state file, so they stay until then
+        assert!(v1["path_entries"][0].get("discovered_paths").is_some());
+    }
+
+    #[test]
+    fn current_files_are_left_alone() {
+        let mut current = table("version = 2\ndefault_excludes = []\n");
+        let before = current.clone();
+        assert_eq!(upgrade(&mut current), Ok(Vec::new()));
+        assert_eq!(current, before);
+    }
+
+    #[test]
+    fn newer_files_are_refused() {
+        let mut newer = table("version = 3\n");
+        assert_eq!(upgrade(&mut newer), Err(3));
+    }
+
+    #[test]
+    fn empty_files_stay_empty() {
+        let mut empty = Table::new();
+        assert_eq!(upgrade(&mut empty), Ok(Vec::new()));
+        assert!(empty.is_empty());
+    }
+}
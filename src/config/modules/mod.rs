@@ -1,3 +1,12 @@
+pub mod backup;
+pub mod cache;
 pub mod config;
+pub mod discover;
 pub mod entry;
 pub mod exclude;
+pub mod layer;
+pub mod lookup;
+pub mod migrate;
+pub mod state;
+pub mod trust;
+pub mod variable;
@@ -0,0 +1,61 @@
+use super::{cache, exclude};
+use crate::utilities::write_atomic;
+use directories::ProjectDirs;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// What scanning found under each entry, kept out of the config so it stays portable
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct State {
+    pub config_path: PathBuf,
+    #[serde(default)]
+    pub discovered: IndexMap<PathBuf, IndexMap<PathBuf, exclude::Check>>,
+    /// Hash of the rules each entry was scanned with, in hex since TOML integers are signed
+    #[serde(default)]
+    pub rules: IndexMap<PathBuf, String>,
+}
+
+impl State {
+    /// `None` when missing, unreadable or kept for another config; scan state can always
+    /// be rebuilt
+    pub fn read(path: &Path, config_path: &Path) -> Option<Self> {
+        toml::from_str::<Self>(&fs::read_to_string(path).ok()?)
+            .ok()
+            .filter(|state| state.config_path == config_path)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Directory under the XDG state dir for what the config at `config_path` produces on this
+/// machine: scan state, caches, the lock and backups
+pub fn dir(config_path: &Path) -> PathBuf {
+    //@ One directory per config, named by its path, so custom configs keep separate state
+    let name = format!(
+        "{:016x}",
+        cache::checksum(config_path.as_os_str().as_encoded_bytes())
+    );
+    match ProjectDirs::from("com", "ccutils", env!("CARGO_PKG_NAME")) {
+        Some(dirs) => dirs
+            .state_dir()
+            .unwrap_or_else(|| dirs.cache_dir())
+            .join(name),
+        None => config_path.with_extension("state"),
+    }
+}
+
+/// Location of the scan state kept for the config at `config_path`
+pub fn path(config_path: &Path) -> PathBuf {
+    dir(config_path).join("state.toml")
+}
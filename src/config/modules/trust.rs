@@ -0,0 +1,48 @@
+use super::state;
+use crate::utilities::write_atomic;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Project configs the user allowed with `dotsrus allow`, each with the content allowed.
+/// The content is kept whole rather than hashed, since a checksum like FNV can be forged
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct Trusted {
+    #[serde(default)]
+    pub files: IndexMap<PathBuf, String>,
+}
+
+impl Trusted {
+    /// Empty when missing or unreadable, so nothing is trusted by accident
+    pub fn read(config_path: &Path) -> Self {
+        fs::read_to_string(path(config_path))
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn write(&self, config_path: &Path) -> Result<(), Box<dyn Error>> {
+        let path = path(config_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(&path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Whether `project` was allowed as it reads now; any edit needs allowing again
+    pub fn allows(&self, project: &Path, content: &str) -> bool {
+        self.files
+            .get(project)
+            .is_some_and(|allowed| allowed == content)
+    }
+}
+
+/// Location of the trusted project configs for the config at `config_path`
+pub fn path(config_path: &Path) -> PathBuf {
+    state::dir(config_path).join("trusted.toml")
+}
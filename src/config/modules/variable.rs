@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Kind {
+    #[default]
+    Plain, // Value is stored as given
+    Path,    // Value is a filesystem path
+    Command, // Value is the location of an executable
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scope {
+    #[default]
+    Local, // Shell variable, not inherited by child processes
+    Export, // Exported to child processes
+}
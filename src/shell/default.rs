@@ -0,0 +1,212 @@
+use super::{elvish, fish, nushell, posix, powershell};
+use crate::{
+    config::entry,
+    utilities::{is_valid_name, KeyError},
+    Config,
+};
+use clap::ValueEnum;
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Sh,
+    Bash,
+    Zsh,
+    Fish,
+    Nu,
+    Powershell,
+    Elvish,
+}
+
+/// Shell-specific syntax used to render the generated environment
+pub trait Syntax {
+    /// Lines written at the top of the generated script
+    fn header(&self) -> String;
+
+    /// Add directories around the inherited PATH, skipping any already on it
+    fn path(&self, prepend: &[PathBuf], append: &[PathBuf]) -> String;
+
+    /// Assign a managed variable
+    fn variable(&self, variable: &entry::Variable) -> String;
+
+    /// Load the generated script from an rc file
+    fn source(&self, script: &Path) -> String;
+}
+
+impl Shell {
+    pub const ALL: [Self; 7] = [
+        Self::Sh,
+        Self::Bash,
+        Self::Zsh,
+        Self::Fish,
+        Self::Nu,
+        Self::Powershell,
+        Self::Elvish,
+    ];
+
+    /// Guess the current shell from $SHELL, falling back to POSIX sh
+    pub fn detect() -> Self {
+        let name = env::var_os("SHELL")
+            .as_deref()
+            .and_then(|shell| Path::new(shell).file_stem())
+            .map(|name| name.to_string_lossy().to_lowercase());
+
+        match name.as_deref() {
+            Some("bash") => Self::Bash,
+            Some("zsh") => Self::Zsh,
+            Some("fish") => Self::Fish,
+            Some("nu") => Self::Nu,
+            Some("pwsh") | Some("powershell") => Self::Powershell,
+            Some("elvish") => Self::Elvish,
+            _ => Self::Sh,
+        }
+    }
+
+    fn syntax(self) -> &'static dyn Syntax {
+        match self {
+            Self::Sh => &posix::Posix { shebang: "/bin/sh" },
+            Self::Bash => &posix::Posix {
+                shebang: "/usr/bin/env bash",
+            },
+            Self::Zsh => &posix::Posix {
+                shebang: "/usr/bin/env zsh",
+            },
+            Self::Fish => &fish::Fish,
+            Self::Nu => &nushell::Nushell,
+            Self::Powershell => &powershell::PowerShell,
+            Self::Elvish => &elvish::Elvish,
+        }
+    }
+
+    /// Extension of the generated script, written next to the config file
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Sh => "env",
+            Self::Bash => "bash",
+            Self::Zsh => "zsh",
+            Self::Fish => "fish",
+            Self::Nu => "nu",
+            Self::Powershell => "ps1",
+            Self::Elvish => "elv",
+        }
+    }
+
+    /// Startup file that loads the generated script, relative to `home`
+    pub fn rc_file(self, home: &Path) -> PathBuf {
+        match self {
+            Self::Sh => home.join(".profile"),
+            Self::Bash => home.join(".bashrc"),
+            Self::Zsh => home.join(".zshrc"),
+            Self::Fish => home.join(".config/fish/config.fish"),
+            Self::Nu => home.join(".config/nushell/config.nu"),
+            Self::Powershell if cfg!(windows) => {
+                home.join("Documents/PowerShell/Microsoft.PowerShell_profile.ps1")
+            }
+            Self::Powershell => home.join(".config/powershell/Microsoft.PowerShell_profile.ps1"),
+            Self::Elvish => home.join(".config/elvish/rc.elv"),
+        }
+    }
+
+    pub fn source(self, script: &Path) -> String {
+        self.syntax().source(script)
+    }
+
+    pub fn render(self, config: &Config) -> Result<String, KeyError> {
+        let (prepend, append) = config.path_directories();
+        self.render_resolved(&prepend, &append, &config.variables)
+    }
+
+    /// Render directories and variables that are already resolved, e.g. from the cache.
+    /// Fails on a variable name that would not be read as a plain name
+    pub fn render_resolved(
+        self,
+        prepend: &[PathBuf],
+        append: &[PathBuf],
+        variables: &[entry::Variable],
+    ) -> Result<String, KeyError> {
+        if let Some(variable) = variables.iter().find(|v| !is_valid_name(&v.name)) {
+            return Err(KeyError::Invalid(variable.name.clone()));
+        }
+
+        let syntax = self.syntax();
+        let mut script = syntax.header();
+
+        //@ Add valid directories to PATH
+        if !prepend.is_empty() || !append.is_empty() {
+            script.push('\n');
+            script.push_str(&syntax.path(prepend, append));
+        }
+
+        //@ Add managed variables
+        if !variables.is_empty() {
+            script.push('\n');
+        }
+        for variable in variables {
+            script.push_str(&syntax.variable(variable));
+        }
+
+        Ok(script)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::variable;
+
+    //@ A quote of each kind and a backslash, which every backend escapes differently
+    const VALUE: &str = r#"it's a\b "c""#;
+
+    fn variable(name: &str) -> entry::Variable {
+        entry::Variable {
+            name: name.to_string(),
+            value: VALUE.to_string(),
+            original: None,
+            kind: variable::Kind::Plain,
+            scope: variable::Scope::Export,
+            lookup_order: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn every_backend_quotes_values_for_its_shell() {
+        let expected = [
+            (Shell::Sh, r#"export NAME='it'\''s a\b "c"'"#),
+            (Shell::Bash, r#"export NAME='it'\''s a\b "c"'"#),
+            (Shell::Zsh, r#"export NAME='it'\''s a\b "c"'"#),
+            (Shell::Fish, r#"set -gx NAME 'it\'s a\\b "c"'"#),
+            (Shell::Nu, r#"$env.NAME = "it's a\\b \"c\"""#),
+            (Shell::Powershell, r#"$env:NAME = 'it''s a\b "c"'"#),
+            (Shell::Elvish, r#"set-env NAME 'it''s a\b "c"'"#),
+        ];
+        for (shell, line) in expected {
+            assert_eq!(
+                shell.syntax().variable(&variable("NAME")),
+                format!("{}\n", line),
+                "{:?}",
+                shell
+            );
+        }
+    }
+
+    #[test]
+    fn posix_paths_are_single_quoted() {
+        let script = Shell::Sh
+            .syntax()
+            .path(&[], &[PathBuf::from("/opt/it's here")]);
+        assert!(script.contains(r#"PATH="${PATH:+$PATH:}"'/opt/it'\''s here'"#));
+    }
+
+    #[test]
+    fn names_a_shell_would_not_read_are_refused() {
+        for shell in Shell::ALL {
+            assert_eq!(
+                shell.render_resolved(&[], &[], &[variable("X; rm -rf ~")]),
+                Err(KeyError::Invalid("X; rm -rf ~".to_string()))
+            );
+        }
+    }
+}
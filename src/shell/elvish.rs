@@ -0,0 +1,45 @@
+use super::Syntax;
+use crate::config::entry;
+use std::path::{Path, PathBuf};
+
+pub struct Elvish;
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+impl Syntax for Elvish {
+    fn header(&self) -> String {
+        "# Generated by dotsrus\n".to_string()
+    }
+
+    fn path(&self, prepend: &[PathBuf], append: &[PathBuf]) -> String {
+        let list = |dirs: &[PathBuf]| {
+            dirs.iter()
+                .map(|dir| quote(&dir.display().to_string()))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let without = |dirs: &str, from: &str| {
+            format!("(each {{|dir| if (not (has-value {dirs} $dir)) {{ put $dir }} }} {from})")
+        };
+
+        let prepend = list(prepend);
+        let append = format!("[{}]", list(append));
+        format!(
+            "set paths = [{prepend} {} {}]\n",
+            without(&format!("[{prepend}]"), "$paths"),
+            without("$paths", &append)
+        )
+    }
+
+    //@ Variables defined inside `eval` don't leak to the caller, so every scope uses set-env
+    fn variable(&self, variable: &entry::Variable) -> String {
+        format!("set-env {} {}\n", variable.name, quote(&variable.value))
+    }
+
+    fn source(&self, script: &Path) -> String {
+        let script = quote(&script.display().to_string());
+        format!("use path\nif (path:is-regular {script}) {{ eval (slurp < {script}) }}")
+    }
+}
@@ -0,0 +1,46 @@
+use super::Syntax;
+use crate::config::{entry, variable};
+use std::path::{Path, PathBuf};
+
+pub struct Fish;
+
+//@ Fish single quotes only treat `\` and `'` specially
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+impl Syntax for Fish {
+    fn header(&self) -> String {
+        "#!/usr/bin/env fish\n".to_string()
+    }
+
+    //@ fish_add_path skips directories already on PATH, unless told to --move them
+    fn path(&self, prepend: &[PathBuf], append: &[PathBuf]) -> String {
+        let mut script = String::new();
+        for (flags, dirs) in [("--prepend --move", prepend), ("--append", append)] {
+            if dirs.is_empty() {
+                continue;
+            }
+            let dirs = dirs
+                .iter()
+                .map(|dir| quote(&dir.display().to_string()))
+                .collect::<Vec<_>>()
+                .join(" ");
+            script.push_str(&format!("fish_add_path --path {flags} {dirs}\n"));
+        }
+        script
+    }
+
+    fn variable(&self, variable: &entry::Variable) -> String {
+        let flags = match variable.scope {
+            variable::Scope::Export => "-gx",
+            variable::Scope::Local => "-g",
+        };
+        format!("set {flags} {} {}\n", variable.name, quote(&variable.value))
+    }
+
+    fn source(&self, script: &Path) -> String {
+        let script = quote(&script.display().to_string());
+        format!("test -f {script}; and source {script}")
+    }
+}
@@ -0,0 +1,8 @@
+mod default;
+mod elvish;
+mod fish;
+mod nushell;
+mod posix;
+mod powershell;
+
+pub use default::{Shell, Syntax};
@@ -0,0 +1,43 @@
+use super::Syntax;
+use crate::config::entry;
+use std::path::{Path, PathBuf};
+
+pub struct Nushell;
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl Syntax for Nushell {
+    fn header(&self) -> String {
+        "# Generated by dotsrus\n".to_string()
+    }
+
+    fn path(&self, prepend: &[PathBuf], append: &[PathBuf]) -> String {
+        let list = |dirs: &[PathBuf]| {
+            dirs.iter()
+                .map(|dir| quote(&dir.display().to_string()))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        format!(
+            "let dotsrus_prepend = [{}]\n\
+             let dotsrus_path = ($env.PATH | split row (char esep))\n\
+             $env.PATH = (\n    \
+             $dotsrus_prepend\n    \
+             ++ ($dotsrus_path | where {{|dir| $dir not-in $dotsrus_prepend }})\n    \
+             ++ ([{}] | where {{|dir| $dir not-in $dotsrus_path }})\n)\n",
+            list(prepend),
+            list(append)
+        )
+    }
+
+    //@ Nushell has no unexported globals that survive `source`, so every scope uses $env
+    fn variable(&self, variable: &entry::Variable) -> String {
+        format!("$env.{} = {}\n", variable.name, quote(&variable.value))
+    }
+
+    fn source(&self, script: &Path) -> String {
+        format!("source {}", quote(&script.display().to_string()))
+    }
+}
@@ -0,0 +1,69 @@
+use super::Syntax;
+use crate::{
+    config::{entry, variable},
+    utilities::shell_quote,
+};
+use std::path::{Path, PathBuf};
+
+/// sh, bash and zsh share the same syntax for everything we emit
+pub struct Posix {
+    pub shebang: &'static str,
+}
+
+impl Syntax for Posix {
+    fn header(&self) -> String {
+        format!("#!{}\n", self.shebang)
+    }
+
+    fn path(&self, prepend: &[PathBuf], append: &[PathBuf]) -> String {
+        let mut script = String::new();
+
+        //@ Prepended entries move ahead even if they are already on PATH
+        if !prepend.is_empty() {
+            script.push_str(concat!(
+                "__dotsrus_prepend() {\n",
+                "  __dotsrus_rest=\":$PATH:\"\n",
+                "  while :; do\n",
+                "    case \"$__dotsrus_rest\" in\n",
+                "    *\":$1:\"*) __dotsrus_rest=${__dotsrus_rest%%\":$1:\"*}:${__dotsrus_rest#*\":$1:\"} ;;\n",
+                "    *) break ;;\n",
+                "    esac\n",
+                "  done\n",
+                "  __dotsrus_rest=${__dotsrus_rest#:}\n",
+                "  __dotsrus_rest=${__dotsrus_rest%:}\n",
+                "  PATH=\"$1${__dotsrus_rest:+:$__dotsrus_rest}\"\n",
+                "}\n",
+            ));
+
+            //@ Prepend in reverse so the first entry ends up first
+            for dir in prepend.iter().rev() {
+                let dir = shell_quote(&dir.display().to_string());
+                script.push_str(&format!("__dotsrus_prepend {dir}\n"));
+            }
+            script.push_str("unset -f __dotsrus_prepend\nunset __dotsrus_rest\n");
+        }
+
+        //@ Appended entries are skipped if they are already on PATH
+        for dir in append {
+            let dir = shell_quote(&dir.display().to_string());
+            script.push_str(&format!(
+                "case \":$PATH:\" in *:{dir}:*) ;; *) PATH=\"${{PATH:+$PATH:}}\"{dir} ;; esac\n"
+            ));
+        }
+        script.push_str("export PATH\n");
+        script
+    }
+
+    fn variable(&self, variable: &entry::Variable) -> String {
+        let assignment = format!("{}={}", variable.name, shell_quote(&variable.value));
+        match variable.scope {
+            variable::Scope::Export => format!("export {assignment}\n"),
+            variable::Scope::Local => format!("{assignment}\n"),
+        }
+    }
+
+    fn source(&self, script: &Path) -> String {
+        let script = shell_quote(&script.display().to_string());
+        format!("[ -f {script} ] && . {script}")
+    }
+}
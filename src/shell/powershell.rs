@@ -0,0 +1,49 @@
+use super::Syntax;
+use crate::config::{entry, variable};
+use std::path::{Path, PathBuf};
+
+pub struct PowerShell;
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+impl Syntax for PowerShell {
+    fn header(&self) -> String {
+        "# Generated by dotsrus\n".to_string()
+    }
+
+    fn path(&self, prepend: &[PathBuf], append: &[PathBuf]) -> String {
+        let list = |dirs: &[PathBuf]| {
+            dirs.iter()
+                .map(|dir| quote(&dir.display().to_string()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        format!(
+            "$dotsrusPrepend = @({})\n\
+             $dotsrusPath = @($env:PATH -split [IO.Path]::PathSeparator)\n\
+             $env:PATH = (\n    \
+             $dotsrusPrepend +\n    \
+             @($dotsrusPath | Where-Object {{ $_ -notin $dotsrusPrepend }}) +\n    \
+             @(@({}) | Where-Object {{ $_ -notin $dotsrusPath }})\n\
+             ) -join [IO.Path]::PathSeparator\n\
+             Remove-Variable dotsrusPrepend, dotsrusPath\n",
+            list(prepend),
+            list(append)
+        )
+    }
+
+    fn variable(&self, variable: &entry::Variable) -> String {
+        let value = quote(&variable.value);
+        match variable.scope {
+            variable::Scope::Export => format!("$env:{} = {value}\n", variable.name),
+            variable::Scope::Local => format!("$global:{} = {value}\n", variable.name),
+        }
+    }
+
+    fn source(&self, script: &Path) -> String {
+        let script = quote(&script.display().to_string());
+        format!("if (Test-Path {script}) {{ . {script} }}")
+    }
+}
@@ -0,0 +1,18 @@
+use directories::BaseDirs;
+use std::path::PathBuf;
+
+//@ Expand a leading `~` to the home directory, leaving other paths untouched
+pub fn expand_home(path: &str) -> PathBuf {
+    let home = || BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+
+    if path == "~" {
+        if let Some(home) = home() {
+            return home;
+        }
+    } else if let Some(rest) = path.strip_prefix("~/") {
+        if let Some(home) = home() {
+            return home.join(rest);
+        }
+    }
+    PathBuf::from(path)
+}
@@ -0,0 +1,26 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    path::Path,
+};
+
+/// Exclusive advisory lock, held until dropped
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    //@ Block until no other process holds the lock at `path`, creating it if needed
+    pub fn acquire(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+        file.lock()?;
+        Ok(Self { _file: file })
+    }
+}
@@ -1,2 +1,16 @@
+mod diff_lines;
+mod executable;
+mod expand_home;
+mod file_lock;
+mod normalize_key;
 mod pattern_match;
-pub use pattern_match::component_matches_pattern;
+mod shell_quote;
+mod write_atomic;
+pub use diff_lines::diff_lines;
+pub use executable::{contains_executable, find_executable, is_executable};
+pub use expand_home::expand_home;
+pub use file_lock::FileLock;
+pub use normalize_key::{is_valid_name, Case, KeyError, KeyNormalizer};
+pub use pattern_match::{component_matches_pattern, split_patterns, PatternSet};
+pub use shell_quote::{shell_quote, shell_quote_if_needed};
+pub use write_atomic::write_atomic;
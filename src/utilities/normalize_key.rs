@@ -0,0 +1,198 @@
+use clap::ValueEnum;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Case {
+    #[default]
+    Preserve, // Keep the case as given
+    Upper, // Force UPPER_CASE
+    Lower, // Force lower_case
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum KeyError {
+    #[error("Key is missing, nothing to normalize")]
+    Missing,
+    #[error("'{0}' is not a valid shell variable name")]
+    Invalid(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyNormalizer {
+    pub delimiter: String,
+    pub case: Case,
+    pub prefix: Option<String>,
+    pub suffix: Option<String>,
+}
+
+impl Default for KeyNormalizer {
+    fn default() -> Self {
+        Self {
+            delimiter: "_".to_string(),
+            case: Case::default(),
+            prefix: None,
+            suffix: None,
+        }
+    }
+}
+
+impl KeyNormalizer {
+    pub fn normalize<S: AsRef<str>>(&self, parts: &[S]) -> Result<String, KeyError> {
+        //@ Join the key parts with the delimiter
+        let key = parts
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(&self.delimiter);
+        if key.is_empty() {
+            return Err(KeyError::Missing);
+        }
+
+        //@ Add prefix and suffix, if provided
+        let key = [
+            self.prefix.as_deref(),
+            Some(key.as_str()),
+            self.suffix.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(&self.delimiter);
+
+        //@ Replace invalid characters with the delimiter
+        let key = clean_string(&key, &self.delimiter);
+        if key.is_empty() {
+            return Err(KeyError::Missing);
+        }
+
+        //@ Modify the case if required
+        let key = match self.case {
+            Case::Preserve => key,
+            Case::Upper => key.to_ascii_uppercase(),
+            Case::Lower => key.to_ascii_lowercase(),
+        };
+
+        if is_valid_name(&key) {
+            Ok(key)
+        } else {
+            Err(KeyError::Invalid(key))
+        }
+    }
+}
+
+//@ Replace runs of invalid characters with a single delimiter and trim the ends
+fn clean_string(value: &str, delimiter: &str) -> String {
+    let mut cleaned = String::with_capacity(value.len());
+    let mut pending = false;
+
+    for c in value.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            if pending && !cleaned.is_empty() {
+                cleaned.push_str(delimiter);
+            }
+            pending = false;
+            cleaned.push(c);
+        } else {
+            pending = true;
+        }
+    }
+    cleaned
+}
+
+//@ Names must match [A-Za-z_][A-Za-z0-9_]* to be accepted by every POSIX shell
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_affixes(prefix: Option<&str>, suffix: Option<&str>) -> KeyNormalizer {
+        KeyNormalizer {
+            prefix: prefix.map(String::from),
+            suffix: suffix.map(String::from),
+            ..KeyNormalizer::default()
+        }
+    }
+
+    #[test]
+    fn parts_are_joined_and_wrapped_in_prefix_and_suffix() {
+        let normalizer = with_affixes(Some("my"), Some("dir"));
+        assert_eq!(
+            normalizer.normalize(&["app", "", "cache"]).unwrap(),
+            "my_app_cache_dir"
+        );
+        assert_eq!(
+            normalizer.normalize(&["app"]).unwrap(),
+            "my_app_dir",
+            "empty parts are skipped"
+        );
+    }
+
+    #[test]
+    fn invalid_characters_collapse_into_one_delimiter() {
+        let normalizer = KeyNormalizer::default();
+        assert_eq!(normalizer.normalize(&["  my -- key  "]).unwrap(), "my_key");
+        assert_eq!(normalizer.normalize(&["ünï"]).unwrap(), "n");
+    }
+
+    #[test]
+    fn case_is_forced_after_cleaning() {
+        let normalizer = KeyNormalizer {
+            case: Case::Upper,
+            ..with_affixes(Some("x"), None)
+        };
+        assert_eq!(normalizer.normalize(&["path-to"]).unwrap(), "X_PATH_TO");
+    }
+
+    #[test]
+    fn a_leading_digit_is_refused() {
+        let normalizer = KeyNormalizer::default();
+        assert_eq!(
+            normalizer.normalize(&["1password"]),
+            Err(KeyError::Invalid("1password".to_string()))
+        );
+        assert!(with_affixes(Some("app"), None)
+            .normalize(&["1password"])
+            .is_ok());
+    }
+
+    #[test]
+    fn a_delimiter_no_shell_accepts_is_refused() {
+        let normalizer = KeyNormalizer {
+            delimiter: "-".to_string(),
+            ..with_affixes(Some("my"), None)
+        };
+        assert_eq!(
+            normalizer.normalize(&["key"]),
+            Err(KeyError::Invalid("my-key".to_string()))
+        );
+    }
+
+    #[test]
+    fn valid_names_start_with_a_letter_or_underscore() {
+        for name in ["PATH", "_private", "a1_B2"] {
+            assert!(is_valid_name(name), "{}", name);
+        }
+        for name in ["", "1st", "my-key", "a b", "x;y", "ünï", "$HOME"] {
+            assert!(!is_valid_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn nothing_left_to_name_is_missing() {
+        let normalizer = KeyNormalizer::default();
+        assert_eq!(normalizer.normalize::<&str>(&[]), Err(KeyError::Missing));
+        assert_eq!(normalizer.normalize(&["", ""]), Err(KeyError::Missing));
+        assert_eq!(normalizer.normalize(&["-!-"]), Err(KeyError::Missing));
+    }
+}
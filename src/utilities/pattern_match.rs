@@ -1,16 +1,230 @@
+use globset::GlobBuilder;
+use ignore::{
+    gitignore::{Gitignore, GitignoreBuilder},
+    Match,
+};
+use regex::Regex;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PatternError {
+    #[error("Invalid glob pattern: {0}")]
+    Glob(#[from] ignore::Error),
+    #[error("Invalid regex pattern '{0}': {1}")]
+    Regex(String, regex::Error),
+}
+
+/// The pattern that decided whether a path is excluded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternMatch {
+    pub pattern: String,
+    pub negated: bool, // `!pattern` re-includes the path
+}
+
+/// Exclude patterns for paths under a root, using gitignore-style globs:
+/// bare words match any component, patterns with a `/` are anchored to the
+/// root, `re:` switches to a regex and `!` re-includes a path.
+/// When several patterns match, the last one wins.
+#[derive(Clone)]
+pub struct PatternSet {
+    root: PathBuf,
+    patterns: Vec<String>,
+    globs: Gitignore,
+    glob_order: HashMap<String, usize>,
+    regexes: Vec<(usize, Regex, bool)>,
+}
+
+impl PatternSet {
+    pub fn new(root: &Path, patterns: &[String]) -> Result<Self, PatternError> {
+        let mut builder = GitignoreBuilder::new(root);
+        builder.case_insensitive(true)?;
+
+        let mut glob_order = HashMap::new();
+        let mut regexes = Vec::new();
+        let patterns: Vec<String> = patterns.iter().map(|p| p.trim().to_string()).collect();
+
+        for (index, pattern) in patterns.iter().enumerate() {
+            let (negated, body) = match pattern.strip_prefix('!') {
+                Some(body) => (true, body),
+                None => (false, pattern.as_str()),
+            };
+
+            if let Some(expression) = body.strip_prefix("re:") {
+                let regex = Regex::new(expression)
+                    .map_err(|err| PatternError::Regex(expression.to_string(), err))?;
+                regexes.push((index, regex, negated));
+            } else if !body.is_empty() {
+                builder.add_line(None, pattern)?;
+                glob_order.insert(pattern.clone(), index);
+            }
+        }
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            patterns,
+            globs: builder.build()?,
+            glob_order,
+            regexes,
+        })
+    }
+
+    /// Whether any pattern re-includes paths with `!`
+    pub fn has_negations(&self) -> bool {
+        self.patterns.iter().any(|pattern| pattern.starts_with('!'))
+    }
+
+    /// Find the pattern that decides `path`, checking its parents under the root too
+    pub fn check(&self, path: &Path, is_dir: bool) -> Option<PatternMatch> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+
+        let glob = match self.globs.matched_path_or_any_parents(path, is_dir) {
+            Match::Ignore(glob) | Match::Whitelist(glob) => self
+                .glob_order
+                .get(glob.original())
+                .map(|index| (*index, glob.is_whitelist())),
+            Match::None => None,
+        };
+
+        //@ Regexes see the relative path of the path itself and of each parent, like globs
+        let components: Vec<_> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        let prefixes: Vec<String> = (1..=components.len())
+            .map(|len| components[..len].join("/"))
+            .collect();
+        let regex = self
+            .regexes
+            .iter()
+            .rev()
+            .find(|(_, regex, _)| prefixes.iter().any(|prefix| regex.is_match(prefix)))
+            .map(|(index, _, negated)| (*index, *negated));
+
+        let (index, negated) = match (glob, regex) {
+            (Some(glob), Some(regex)) => glob.max(regex),
+            (glob, regex) => glob.or(regex)?,
+        };
+        Some(PatternMatch {
+            pattern: self.patterns[index].clone(),
+            negated,
+        })
+    }
+}
+
+//@ Split a comma-separated list, leaving commas inside `{a,b}` and `[a,b]` alone
+pub fn split_patterns(list: &str) -> Vec<String> {
+    let mut patterns = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+
+    for c in list.chars() {
+        match c {
+            '{' | '[' => depth += 1,
+            '}' | ']' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                patterns.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    patterns.push(current);
+
+    patterns
+        .into_iter()
+        .map(|pattern| pattern.trim().to_string())
+        .filter(|pattern| !pattern.is_empty())
+        .collect()
+}
+
 pub fn component_matches_pattern(component: &str, pattern: &str) -> bool {
-    let component = component.to_lowercase();
-
-    if pattern.starts_with('*') && pattern.ends_with('*') {
-        let inner = &pattern[1..pattern.len() - 1];
-        component.contains(inner)
-    } else if pattern.starts_with('*') {
-        let suffix = pattern.strip_prefix('*').unwrap_or(pattern);
-        component.ends_with(suffix)
-    } else if pattern.ends_with('*') {
-        let prefix = pattern.strip_suffix('*').unwrap_or(pattern);
-        component.starts_with(prefix)
-    } else {
-        component == pattern
+    match GlobBuilder::new(pattern).case_insensitive(true).build() {
+        Ok(glob) => glob.compile_matcher().is_match(component),
+        Err(_) => component.eq_ignore_ascii_case(pattern),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(patterns: &[&str]) -> PatternSet {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        PatternSet::new(Path::new("/root"), &patterns).unwrap()
+    }
+
+    fn excluded_by(set: &PatternSet, relative: &str) -> Option<String> {
+        set.check(&Path::new("/root").join(relative), true)
+            .filter(|found| !found.negated)
+            .map(|found| found.pattern)
+    }
+
+    #[test]
+    fn bare_words_match_any_component_and_what_is_below() {
+        let set = set(&["node_modules", "tmp"]);
+        assert_eq!(
+            excluded_by(&set, "node_modules").as_deref(),
+            Some("node_modules")
+        );
+        assert_eq!(
+            excluded_by(&set, "a/node_modules/bin").as_deref(),
+            Some("node_modules")
+        );
+        assert_eq!(excluded_by(&set, "TMP").as_deref(), Some("tmp"));
+        assert_eq!(excluded_by(&set, "a/tmpfiles"), None);
+    }
+
+    #[test]
+    fn patterns_with_a_slash_are_anchored_to_the_root() {
+        let set = set(&["tools/old"]);
+        assert!(excluded_by(&set, "tools/old/bin").is_some());
+        assert!(excluded_by(&set, "x/tools/old").is_none());
+    }
+
+    #[test]
+    fn regexes_match_the_path_and_its_parents() {
+        let set = set(&["re:^vendor$", "old"]);
+        assert_eq!(excluded_by(&set, "vendor").as_deref(), Some("re:^vendor$"));
+        assert_eq!(
+            excluded_by(&set, "vendor/bin").as_deref(),
+            Some("re:^vendor$")
+        );
+        assert_eq!(excluded_by(&set, "old/bin").as_deref(), Some("old"));
+        assert_eq!(excluded_by(&set, "a/vendor"), None);
+    }
+
+    #[test]
+    fn the_last_matching_pattern_wins() {
+        let set = set(&["re:^build", "!re:^build/bin$"]);
+        assert!(excluded_by(&set, "build/lib").is_some());
+        assert_eq!(
+            set.check(Path::new("/root/build/bin"), true),
+            Some(PatternMatch {
+                pattern: "!re:^build/bin$".to_string(),
+                negated: true,
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_regexes_are_reported() {
+        let patterns = vec!["re:(".to_string()];
+        assert!(matches!(
+            PatternSet::new(Path::new("/root"), &patterns),
+            Err(PatternError::Regex(..))
+        ));
+    }
+
+    #[test]
+    fn split_keeps_commas_inside_braces_and_brackets() {
+        assert_eq!(
+            split_patterns("a, {b,c}, [d,e] ,"),
+            vec!["a", "{b,c}", "[d,e]"]
+        );
     }
 }
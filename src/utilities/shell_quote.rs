@@ -0,0 +1,18 @@
+//@ Wrap a value in single quotes so the shell takes it literally
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+//@ Quote a value only when the shell would otherwise interpret it
+pub fn shell_quote_if_needed(value: &str) -> String {
+    let is_safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-+=.,:/@%".contains(c));
+
+    if is_safe {
+        value.to_string()
+    } else {
+        shell_quote(value)
+    }
+}
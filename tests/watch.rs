@@ -0,0 +1,171 @@
+use std::{
+    env, fs,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    process::{self, Child, ChildStdout, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Poll until `condition` holds, giving up after a few seconds
+fn wait_for(condition: impl Fn() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if condition() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    false
+}
+
+fn script_mentions(script: &Path, dir: &Path) -> bool {
+    fs::read_to_string(script)
+        .map(|content| content.contains(&*dir.to_string_lossy()))
+        .unwrap_or(false)
+}
+
+/// A temporary home with a config managing `tree`, which has a `bin` directory.
+/// Dropping it stops any watcher and removes everything, even when an assert failed
+struct Sandbox {
+    base: PathBuf,
+    config: PathBuf,
+    relative: bool, // Pass `--config` relative to the config's directory
+    watcher: Option<(Child, BufReader<ChildStdout>)>,
+}
+
+impl Sandbox {
+    fn new(name: &str, relative: bool) -> Self {
+        let base = env::temp_dir().join(format!("dotsrus-{}-{}", name, process::id()));
+        fs::create_dir_all(base.join("tree").join("bin")).unwrap();
+        fs::create_dir_all(base.join("config")).unwrap();
+        let base = base.canonicalize().unwrap();
+        let config = base.join("config").join("config.toml");
+        let sandbox = Self {
+            base,
+            config,
+            relative,
+            watcher: None,
+        };
+
+        let added = sandbox
+            .dotsrus()
+            .args(["path", "add"])
+            .arg(sandbox.tree())
+            .output()
+            .unwrap();
+        assert!(added.status.success(), "{:?}", added);
+        assert!(script_mentions(
+            &sandbox.script(),
+            &sandbox.tree().join("bin")
+        ));
+        sandbox
+    }
+
+    fn tree(&self) -> PathBuf {
+        self.base.join("tree")
+    }
+
+    fn script(&self) -> PathBuf {
+        self.config.with_extension("env")
+    }
+
+    fn dotsrus(&self) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_dotsrus"));
+        command.env("HOME", &self.base).arg("--config");
+        if self.relative {
+            command
+                .current_dir(self.config.parent().unwrap())
+                .arg(self.config.file_name().unwrap());
+        } else {
+            command.arg(&self.config);
+        }
+        command
+    }
+
+    /// Start `dotsrus watch`, returning once it is watching
+    fn watch(&mut self) {
+        let mut watcher = self
+            .dotsrus()
+            .args(["watch", "--debounce", "100"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        //@ Keep the reader alive, so the watcher's later output has somewhere to go
+        let output = BufReader::new(watcher.stdout.take().unwrap());
+        let (_, output) = self.watcher.insert((watcher, output));
+        let mut ready = String::new();
+        output.read_line(&mut ready).unwrap();
+        assert!(ready.starts_with("Watching"), "{}", ready);
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        if let Some((mut watcher, _)) = self.watcher.take() {
+            let _ = watcher.kill();
+            let _ = watcher.wait();
+        }
+        let _ = fs::remove_dir_all(&self.base);
+    }
+}
+
+#[test]
+fn watch_tracks_directories_appearing_and_disappearing() {
+    let mut sandbox = Sandbox::new("watch", false);
+    sandbox.watch();
+
+    let new_dir = sandbox.tree().join("tools").join("bin");
+    fs::create_dir_all(&new_dir).unwrap();
+    assert!(
+        wait_for(|| script_mentions(&sandbox.script(), &new_dir)),
+        "new directory was not added to the script"
+    );
+
+    fs::remove_dir_all(sandbox.tree().join("tools")).unwrap();
+    assert!(
+        wait_for(|| !script_mentions(&sandbox.script(), &new_dir)),
+        "removed directory was not dropped from the script"
+    );
+}
+
+#[test]
+fn watch_applies_edited_excludes_to_known_directories() {
+    let mut sandbox = Sandbox::new("watch-edit", false);
+    sandbox.watch();
+    assert_edit_applies(&sandbox);
+}
+
+#[test]
+fn watch_notices_edits_to_a_config_given_by_a_relative_path() {
+    let mut sandbox = Sandbox::new("watch-relative", true);
+
+    //@ The unit runs from another directory, so it must name the config absolutely
+    let unit = sandbox
+        .dotsrus()
+        .args(["watch", "--unit"])
+        .output()
+        .unwrap();
+    let unit = String::from_utf8_lossy(&unit.stdout);
+    assert!(
+        unit.contains(&format!("--config \"{}\"", sandbox.config.display())),
+        "{}",
+        unit
+    );
+
+    sandbox.watch();
+    assert_edit_applies(&sandbox);
+}
+
+/// Exclude `bin` by editing the config, and wait for the watcher to drop it
+fn assert_edit_applies(sandbox: &Sandbox) {
+    //@ The directory itself is untouched, so only the new rules can drop it
+    let content = fs::read_to_string(&sandbox.config).unwrap();
+    let edited = content.replacen("default_excludes = [", "default_excludes = [\"bin\",", 1);
+    assert_ne!(content, edited);
+    fs::write(&sandbox.config, edited).unwrap();
+    assert!(
+        wait_for(|| !script_mentions(&sandbox.script(), &sandbox.tree().join("bin"))),
+        "directory matching the new exclude stayed in the script"
+    );
+}